@@ -1,12 +1,13 @@
 import * as anchor from '@project-serum/anchor';
 import { AnchorProvider, BN } from '@project-serum/anchor';
 import NodeWallet from '@project-serum/anchor/dist/cjs/nodewallet';
-import { clusterApiUrl, Keypair, LAMPORTS_PER_SOL, SystemProgram, SYSVAR_RECENT_BLOCKHASHES_PUBKEY } from '@solana/web3.js';
+import { clusterApiUrl, Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RECENT_BLOCKHASHES_PUBKEY } from '@solana/web3.js';
 
 import { AnchorUsingVrf, IDL } from './target/types/anchor_using_vrf';
 
 const DEFAULT_COMMITMENT = 'confirmed';
 const PROGRAM_ID = '3gfec8ANuaWzkNhAR5QRjUvGqUjMYLJ3YnSVhgMkugqv';
+const VRF_COORDINATOR_ID = 'Ak7CBLEMGKuqAbiVpCV5iV9BmVBAv5ZC7hdawe72DBuw';
 
 const OWNER_KEYPAIR = Keypair.fromSecretKey(new Uint8Array([
 	136, 226, 76, 65, 7, 20, 124, 252, 73, 106, 66, 109, 104, 240, 81, 246, 2,
@@ -84,6 +85,7 @@ const sleep = (time: number) => new Promise(resolve => setTimeout(resolve, time)
 		}
 		case 'play': {
 			const programState = await client.account.programState.fetch(statePubkey);
+			console.log('Round: ', programState.round.toString());
 			const [vrfPubkey, vrfBump] = anchor.utils.publicKey.findProgramAddressSync(
 				[
 					Buffer.from('vrf', 'utf-8'),
@@ -93,12 +95,24 @@ const sleep = (time: number) => new Promise(resolve => setTimeout(resolve, time)
 				client.programId,
 			);
 
+			// The oracle registered by the vrf-server, using the owner as fee payer
+			const [oraclePubkey, oracleBump] = anchor.utils.publicKey.findProgramAddressSync(
+				[
+					Buffer.from('oracle', 'utf-8'),
+					programState.owner.toBuffer(),
+				],
+				new PublicKey(VRF_COORDINATOR_ID),
+			);
+
 			const tx = await client.methods
 				.flipACoin(new BN(10))
 				.accounts({
 					user: USER_KEYPAIR.publicKey,
 					state: statePubkey,
 					vrf: vrfPubkey,
+					oracle: oraclePubkey,
+					// The vrf-server use its fee payer as fee vault unless configured otherwise
+					feeVault: programState.owner,
 					systemProgram: SystemProgram.programId,
 					recentSlothashes: SYSVAR_RECENT_BLOCKHASHES_PUBKEY,
 				})
@@ -109,6 +123,31 @@ const sleep = (time: number) => new Promise(resolve => setTimeout(resolve, time)
 			console.log(trans.meta.logMessages);
 			break;
 		}
+		case 'cancel': {
+			// the round of the coin flip to cancel, printed when playing
+			const round = new BN(process.argv[3]);
+			const [vrfPubkey, vrfBump] = anchor.utils.publicKey.findProgramAddressSync(
+				[
+					Buffer.from('vrf', 'utf-8'),
+					USER_KEYPAIR.publicKey.toBuffer(),
+					round.toBuffer('be', 8),
+				],
+				client.programId,
+			);
+
+			const tx = await client.methods
+				.cancelCoinFlip(round)
+				.accounts({
+					user: USER_KEYPAIR.publicKey,
+					vrf: vrfPubkey,
+				})
+				.signers([ USER_KEYPAIR ])
+				.rpc({ commitment: DEFAULT_COMMITMENT });
+
+			const trans = await provider.connection.getTransaction(tx, { commitment: DEFAULT_COMMITMENT });
+			console.log(trans.meta.logMessages);
+			break;
+		}
 		default:
 			console.log('unknown command', process.argv);
 			break;
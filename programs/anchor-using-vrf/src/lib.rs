@@ -3,7 +3,29 @@ use solana_program::sysvar::recent_blockhashes;
 
 declare_id!("3gfec8ANuaWzkNhAR5QRjUvGqUjMYLJ3YnSVhgMkugqv");
 
-vrf_sdk::declare_vrf_state!(VrfState);
+vrf_sdk::declare_compact_vrf_state!(VrfState);
+
+/// Seconds after which a user can cancel a coin flip the oracle did not answer.
+pub const VRF_TIMEOUT: i64 = 10 * 60;
+
+/// Size of a compact `VrfState` requesting the `on_coin_flipped` callback.
+fn vrf_account_size() -> usize {
+    vrf_sdk::account_size_for(
+        &accounts::CoinFlipResult {
+            owner: Pubkey::default(),
+            state: Pubkey::default(),
+            user: Pubkey::default(),
+            vrf: Pubkey::default(),
+            oracle: Pubkey::default(),
+        },
+        &instruction::OnCoinFlipped {
+            vrf_result: Default::default(),
+            vrf_proof: Default::default(),
+            bet_amount: 0,
+            _vrf_round: 0,
+        },
+    )
+}
 
 #[program]
 mod anchor_using_vrf {
@@ -22,19 +44,28 @@ mod anchor_using_vrf {
         let data = ctx.accounts.recent_slothashes.data.borrow();
         let most_recent_slothash = &data[4..36];
 
-        vrf_sdk::request_randomness(
+        vrf_sdk::request_randomness_with_fee(
             most_recent_slothash,
             &ctx.accounts.vrf,
+            &ctx.accounts.oracle,
+            // Only charged if the oracle ask for a fee
+            Some(&vrf_sdk::oracle::FeePayer::Lamports {
+                payer: &ctx.accounts.user.to_account_info(),
+                fee_vault: &ctx.accounts.fee_vault,
+                system_program: &ctx.accounts.system_program.to_account_info(),
+            }),
             // This struct will be auto generated by anchor
             accounts::CoinFlipResult {
                 owner: ctx.accounts.state.owner,
                 state: ctx.accounts.state.key(),
                 user: ctx.accounts.user.key(),
                 vrf: ctx.accounts.vrf.key(),
+                oracle: ctx.accounts.oracle.key(),
             },
             // This struct will be auto generated by anchor
             instruction::OnCoinFlipped {
                 vrf_result: Default::default(),
+                vrf_proof: Default::default(),
                 bet_amount: bet_amount,
                 _vrf_round: ctx.accounts.state.round,
             },
@@ -50,11 +81,18 @@ mod anchor_using_vrf {
     pub fn on_coin_flipped(
         ctx: Context<CoinFlipResult>,
         vrf_result: vrf_sdk::VrfResult,
+        vrf_proof: vrf_sdk::VrfProof,
         bet_amount: u64,
         _vrf_round: u64,
     ) -> anchor_lang::Result<()> {
-        // save the result
-        ctx.accounts.vrf.load_mut()?.vrf.result = vrf_result;
+        // save the result and make sure it was generated by the oracle
+        {
+            let mut vrf = ctx.accounts.vrf.load_mut()?;
+            vrf.result = vrf_result;
+            vrf.proof = vrf_proof.proof;
+            vrf.verify_oracle(&ctx.accounts.oracle)?;
+            vrf.set_fulfilled()?;
+        }
 
         let result = vrf_result.random(0..=1)?;
         if result == 0 {
@@ -67,6 +105,17 @@ mod anchor_using_vrf {
 
         Ok(())
     }
+
+    pub fn cancel_coin_flip(
+        ctx: Context<CoinFlipCancel>,
+        _vrf_round: u64,
+    ) -> anchor_lang::Result<()> {
+        vrf_sdk::cancel_request(&ctx.accounts.vrf, VRF_TIMEOUT)?;
+
+        // TODO: Refund bet_amount to user wallet
+
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -97,7 +146,7 @@ pub struct CoinFlipRequest<'info> {
     #[account(
 		init,
 		payer = user,
-		space = vrf_sdk::ACCOUNT_SIZE,
+		space = vrf_account_size(),
 		seeds = [
 			b"vrf",
 			user.key().as_ref(),
@@ -107,6 +156,14 @@ pub struct CoinFlipRequest<'info> {
 	)]
     vrf: AccountLoader<'info, VrfState>,
 
+    /// CHECK: The oracle registered by the owner, checked by `request_randomness`
+    #[account(address = vrf_sdk::oracle::address(&state.owner))]
+    oracle: AccountInfo<'info>,
+
+    /// CHECK: The oracle fee vault, checked by `request_randomness_with_fee`
+    #[account(mut)]
+    fee_vault: AccountInfo<'info>,
+
     /// CHECK:
     #[account(address = recent_blockhashes::ID)]
     recent_slothashes: AccountInfo<'info>,
@@ -115,7 +172,7 @@ pub struct CoinFlipRequest<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(vrf_result: vrf_sdk::VrfResult, bet_amount: u64, _vrf_round: u64)]
+#[instruction(vrf_result: vrf_sdk::VrfResult, vrf_proof: vrf_sdk::VrfProof, bet_amount: u64, _vrf_round: u64)]
 pub struct CoinFlipResult<'info> {
     owner: Signer<'info>,
 
@@ -137,6 +194,28 @@ pub struct CoinFlipResult<'info> {
 		bump
 	)]
     vrf: AccountLoader<'info, VrfState>,
+
+    /// CHECK: checked by `verify_oracle`
+    oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_vrf_round: u64)]
+pub struct CoinFlipCancel<'info> {
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(
+		mut,
+		close = user,
+		seeds = [
+			b"vrf",
+			user.key().as_ref(),
+			&_vrf_round.to_be_bytes(),
+		],
+		bump
+	)]
+    vrf: AccountLoader<'info, VrfState>,
 }
 
 #[account]
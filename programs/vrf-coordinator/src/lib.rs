@@ -0,0 +1,397 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+    },
+};
+use vrf_sdk::{
+    coordinator::{self, AUTHORITY_SEED},
+    ecvrf::VrfPublicKey,
+    oracle::{FeePayer, Oracle, OracleStatus, ORACLE_SEED},
+    vrf::{
+        AccountMetaPacked, CallbackLookup, VrfRequestCancelled, VrfRequestRandomness,
+        ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+    },
+    VrfProof, VrfResult,
+};
+
+declare_id!("Ak7CBLEMGKuqAbiVpCV5iV9BmVBAv5ZC7hdawe72DBuw");
+
+vrf_sdk::declare_vrf_state!(VrfState);
+
+#[program]
+pub mod vrf_coordinator {
+    use super::*;
+
+    pub fn register_oracle(
+        ctx: Context<RegisterOracle>,
+        vrf_public_key: VrfPublicKey,
+    ) -> anchor_lang::Result<()> {
+        *ctx.accounts.oracle = Oracle {
+            authority: ctx.accounts.authority.key(),
+            vrf_public_key,
+            status: OracleStatus::Active,
+            fee: 0,
+            fee_mint: Pubkey::default(),
+            fee_vault: ctx.accounts.authority.key(),
+        };
+
+        Ok(())
+    }
+
+    /// Set the fee of each request, `fee_mint` is `Pubkey::default()` for lamports.
+    pub fn set_oracle_fee(
+        ctx: Context<UpdateOracle>,
+        fee: u64,
+        fee_mint: Pubkey,
+        fee_vault: Pubkey,
+    ) -> anchor_lang::Result<()> {
+        let oracle = &mut ctx.accounts.oracle;
+        oracle.fee = fee;
+        oracle.fee_mint = fee_mint;
+        oracle.fee_vault = fee_vault;
+
+        Ok(())
+    }
+
+    pub fn set_oracle_status(
+        ctx: Context<UpdateOracle>,
+        status: OracleStatus,
+    ) -> anchor_lang::Result<()> {
+        ctx.accounts.oracle.status = status;
+
+        Ok(())
+    }
+
+    pub fn request<'info>(
+        ctx: Context<'_, '_, '_, 'info, Request<'info>>,
+        seeds: Vec<u8>,
+        callback_program_id: Pubkey,
+        callback_accounts: Vec<AccountMetaPacked>,
+        callback_ix_data: Vec<u8>,
+    ) -> anchor_lang::Result<()> {
+        process_request(
+            ctx,
+            seeds,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            None,
+        )
+    }
+
+    /// Same as `request`, appending the accounts of an address lookup table
+    /// to the callback accounts.
+    pub fn request_with_lookup<'info>(
+        ctx: Context<'_, '_, '_, 'info, Request<'info>>,
+        seeds: Vec<u8>,
+        callback_program_id: Pubkey,
+        callback_accounts: Vec<AccountMetaPacked>,
+        callback_ix_data: Vec<u8>,
+        lookup: CallbackLookup,
+    ) -> anchor_lang::Result<()> {
+        process_request(
+            ctx,
+            seeds,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            Some(lookup),
+        )
+    }
+
+    pub fn fulfill<'info>(
+        ctx: Context<'_, '_, '_, 'info, Fulfill<'info>>,
+        result: VrfResult,
+        proof: VrfProof,
+    ) -> anchor_lang::Result<()> {
+        // The borrow must be released before invoking the callback
+        let (callback, callback_accounts) = {
+            let mut vrf = ctx.accounts.vrf.load_mut()?;
+            require!(!vrf.is_fulfilled(), CoordinatorError::AlreadyFulfilled);
+
+            vrf.result = result;
+            vrf.proof = proof.proof;
+            vrf.verify(&ctx.accounts.oracle.vrf_public_key.0)?;
+            vrf.set_fulfilled()?;
+
+            // the lookup table is passed after the callback accounts
+            let callback_accounts = if vrf.lookup.is_used() {
+                let table = ctx
+                    .remaining_accounts
+                    .last()
+                    .ok_or(CoordinatorError::LookupTableMismatch)?;
+                require_keys_eq!(
+                    table.key(),
+                    vrf.lookup.table,
+                    CoordinatorError::LookupTableMismatch
+                );
+                require_keys_eq!(
+                    *table.owner,
+                    ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+                    CoordinatorError::LookupTableMismatch
+                );
+
+                let data = table.try_borrow_data()?;
+                vrf.callback_account_metas(Some(&data))?
+            } else {
+                vrf.callback_account_metas(None)?
+            };
+
+            (vrf.callback, callback_accounts)
+        };
+
+        // Only the coordinator authority sign the callback, whatever the stored accounts say,
+        // so the signature of the fulfiller never reach the callback program
+        let authority = ctx.accounts.authority.key();
+        let callback_accounts = callback_accounts
+            .into_iter()
+            .map(|acc| AccountMeta {
+                is_signer: acc.is_signer && acc.pubkey == authority,
+                ..acc
+            })
+            .collect();
+
+        require_keys_eq!(
+            callback.program_id,
+            ctx.accounts.callback_program.key(),
+            CoordinatorError::CallbackProgramMismatch
+        );
+
+        // VrfProof is optional, only supplied if the callback ask for it
+        let ix_data = callback
+            .fill_ix_data(&result.result, &proof.proof)
+            .ok_or(CoordinatorError::VrfResultNotFound)?;
+
+        let ix = Instruction {
+            program_id: callback.program_id,
+            accounts: callback_accounts,
+            data: ix_data,
+        };
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.authority.to_account_info());
+        account_infos.push(ctx.accounts.callback_program.to_account_info());
+
+        let bump = *ctx.bumps.get("authority").unwrap();
+        invoke_signed(&ix, &account_infos, &[&[AUTHORITY_SEED, &[bump]]])?;
+
+        Ok(())
+    }
+
+    pub fn cancel(ctx: Context<Cancel>) -> anchor_lang::Result<()> {
+        ctx.accounts
+            .vrf
+            .load_mut()?
+            .cancel(vrf_sdk::coordinator::REQUEST_TIMEOUT)?;
+
+        emit!(VrfRequestCancelled {
+            vrf: ctx.accounts.vrf.key()
+        });
+
+        Ok(())
+    }
+
+    pub fn close(_ctx: Context<Close>) -> anchor_lang::Result<()> {
+        Ok(())
+    }
+
+    /// Upgrade an in-flight vrf account to the current layout version.
+    pub fn migrate(ctx: Context<Migrate>) -> anchor_lang::Result<()> {
+        vrf_sdk::migrate_account(&ctx.accounts.vrf)
+    }
+}
+
+fn process_request<'info>(
+    ctx: Context<'_, '_, '_, 'info, Request<'info>>,
+    seeds: Vec<u8>,
+    callback_program_id: Pubkey,
+    callback_accounts: Vec<AccountMetaPacked>,
+    callback_ix_data: Vec<u8>,
+    lookup: Option<CallbackLookup>,
+) -> anchor_lang::Result<()> {
+    // Only the callback program can sign for its requester PDA,
+    // so a request can only invoke the program opening it
+    require_keys_eq!(
+        ctx.accounts.requester.key(),
+        coordinator::requester(&callback_program_id),
+        CoordinatorError::InvalidRequester
+    );
+
+    let authority = coordinator::authority();
+    let callback_accounts = callback_accounts
+        .iter()
+        .map(|acc| AccountMeta {
+            pubkey: acc.pubkey,
+            is_signer: acc.is_signer,
+            is_writable: acc.is_writable,
+        })
+        .collect::<Vec<_>>();
+    require!(
+        callback_accounts
+            .iter()
+            .all(|acc| !acc.is_signer || acc.pubkey == authority),
+        CoordinatorError::InvalidCallbackSigner
+    );
+
+    ctx.accounts.oracle.require_active()?;
+
+    // remaining_accounts: the accounts paying the oracle fee, if any
+    let fee_payer = match ctx.remaining_accounts {
+        [] => None,
+        accounts => {
+            Some(FeePayer::from_accounts(accounts).ok_or(CoordinatorError::InvalidFeeAccounts)?)
+        }
+    };
+    let fee_paid = ctx.accounts.oracle.pay_fee(fee_payer.as_ref())?;
+
+    let round = {
+        let mut vrf = ctx.accounts.vrf.load_init()?;
+        vrf.set_request(
+            &seeds,
+            ctx.accounts.oracle.key(),
+            callback_program_id,
+            &callback_accounts,
+            &callback_ix_data,
+        )?;
+        if let Some(lookup) = &lookup {
+            vrf.set_callback_lookup(lookup)?;
+        }
+        vrf.requester = ctx.accounts.requester.key();
+        vrf.fee_paid = fee_paid;
+        vrf.round
+    };
+
+    emit!(VrfRequestRandomness {
+        vrf: ctx.accounts.vrf.key(),
+        round,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RegisterOracle<'info> {
+    /// The fee payer of the VRF-server
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    #[account(
+		init,
+		payer = authority,
+		space = Oracle::SIZE,
+		seeds = [ ORACLE_SEED, authority.key().as_ref() ],
+		bump
+	)]
+    oracle: Account<'info, Oracle>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracle<'info> {
+    authority: Signer<'info>,
+
+    #[account(mut, has_one = authority)]
+    oracle: Account<'info, Oracle>,
+}
+
+#[derive(Accounts)]
+pub struct Request<'info> {
+    /// The PDA of the requesting program, see `vrf_sdk::coordinator::requester`
+    requester: Signer<'info>,
+
+    oracle: Account<'info, Oracle>,
+
+    /// Created by the requesting program with `owner = vrf_sdk::coordinator::ID`
+    #[account(zero)]
+    vrf: AccountLoader<'info, VrfState>,
+}
+
+#[derive(Accounts)]
+pub struct Fulfill<'info> {
+    fulfiller: Signer<'info>,
+
+    #[account(constraint = oracle.authority == fulfiller.key() @ CoordinatorError::OracleMismatch)]
+    oracle: Account<'info, Oracle>,
+
+    #[account(
+		mut,
+		constraint = vrf.load()?.oracle == oracle.key() @ CoordinatorError::OracleMismatch,
+	)]
+    vrf: AccountLoader<'info, VrfState>,
+
+    /// CHECK: PDA used to sign the callback
+    #[account(seeds = [ AUTHORITY_SEED ], bump)]
+    authority: UncheckedAccount<'info>,
+
+    /// CHECK: We checked that it is the program stored in the vrf callback
+    #[account(executable)]
+    callback_program: UncheckedAccount<'info>,
+    // remaining_accounts: the accounts of the callback instruction,
+    // followed by the address lookup table if the callback uses one
+}
+
+#[derive(Accounts)]
+pub struct Cancel<'info> {
+    requester: Signer<'info>,
+
+    /// CHECK: chosen by the requesting program, receive the rent
+    #[account(mut)]
+    receiver: UncheckedAccount<'info>,
+
+    #[account(
+		mut,
+		close = receiver,
+		constraint = vrf.load()?.requester == requester.key() @ CoordinatorError::RequesterMismatch,
+	)]
+    vrf: AccountLoader<'info, VrfState>,
+}
+
+#[derive(Accounts)]
+pub struct Close<'info> {
+    requester: Signer<'info>,
+
+    /// CHECK: chosen by the requesting program, receive the rent
+    #[account(mut)]
+    receiver: UncheckedAccount<'info>,
+
+    #[account(
+		mut,
+		close = receiver,
+		constraint = vrf.load()?.requester == requester.key() @ CoordinatorError::RequesterMismatch,
+		constraint = vrf.load()?.is_fulfilled() @ CoordinatorError::NotFulfilled,
+	)]
+    vrf: AccountLoader<'info, VrfState>,
+}
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    #[account(mut)]
+    vrf: AccountLoader<'info, VrfState>,
+}
+
+#[error_code]
+pub enum CoordinatorError {
+    #[msg("The vrf request has already been fulfilled")]
+    AlreadyFulfilled,
+    #[msg("The vrf request has not been fulfilled yet")]
+    NotFulfilled,
+    #[msg("The callback program does not match the vrf request")]
+    CallbackProgramMismatch,
+    #[msg("Cannot find VrfResult in the callback instruction data")]
+    VrfResultNotFound,
+    #[msg("Only the requester can close the vrf account")]
+    RequesterMismatch,
+    #[msg("The vrf request is bound to another oracle")]
+    OracleMismatch,
+    #[msg("Unexpected fee payer accounts")]
+    InvalidFeeAccounts,
+    #[msg("The lookup table does not match the vrf request")]
+    LookupTableMismatch,
+    #[msg("Only the coordinator authority can sign the callback")]
+    InvalidCallbackSigner,
+    #[msg("The requester is not the PDA of the callback program")]
+    InvalidRequester,
+}
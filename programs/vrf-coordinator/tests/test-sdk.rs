@@ -0,0 +1,78 @@
+use anchor_lang::Discriminator;
+
+#[test]
+fn test_id() {
+    assert_eq!(vrf_sdk::coordinator::ID, vrf_coordinator::ID);
+}
+
+#[test]
+fn test_instruction_discriminator() {
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::REQUEST,
+        vrf_coordinator::instruction::Request::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::REQUEST_WITH_LOOKUP,
+        vrf_coordinator::instruction::RequestWithLookup::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::FULFILL,
+        vrf_coordinator::instruction::Fulfill::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::CANCEL,
+        vrf_coordinator::instruction::Cancel::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::CLOSE,
+        vrf_coordinator::instruction::Close::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::MIGRATE,
+        vrf_coordinator::instruction::Migrate::DISCRIMINATOR
+    );
+}
+
+#[test]
+fn test_oracle_instruction_discriminator() {
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::REGISTER_ORACLE,
+        vrf_coordinator::instruction::RegisterOracle::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::SET_ORACLE_STATUS,
+        vrf_coordinator::instruction::SetOracleStatus::DISCRIMINATOR
+    );
+    assert_eq!(
+        vrf_sdk::coordinator::instruction::SET_ORACLE_FEE,
+        vrf_coordinator::instruction::SetOracleFee::DISCRIMINATOR
+    );
+}
+
+#[test]
+fn test_oracle_account_discriminator() {
+    let hash = anchor_lang::solana_program::hash::hash(b"account:Oracle");
+    assert_eq!(vrf_sdk::oracle::Oracle::DISCRIMINATOR, hash.to_bytes()[..8]);
+}
+
+#[test]
+fn test_oracle_size() {
+    use anchor_lang::AccountSerialize;
+    use vrf_sdk::{
+        ecvrf::VrfPublicKey,
+        oracle::{Oracle, OracleStatus},
+    };
+
+    let oracle = Oracle {
+        authority: Default::default(),
+        vrf_public_key: VrfPublicKey([2; 33]),
+        status: OracleStatus::Active,
+        fee: 5_000,
+        fee_mint: Default::default(),
+        fee_vault: Default::default(),
+    };
+
+    let mut data = Vec::new();
+    oracle.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), Oracle::SIZE);
+}
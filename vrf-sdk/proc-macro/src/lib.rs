@@ -1,6 +1,9 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::Ident;
+use syn::{
+    parse::{Parse, ParseStream, Parser},
+    parse_quote, Attribute, Data, DeriveInput, Field, Fields, Ident, Path, Token, Visibility,
+};
 
 /// Declare a `VrfState` wrapper struct for [`VrfAccountData`].
 /// We willuse this struct to interact with the `vrf_sdk`
@@ -24,19 +27,198 @@ use syn::Ident;
 #[proc_macro]
 pub fn declare_vrf_state(item: TokenStream) -> TokenStream {
     let struct_name = syn::parse_macro_input!(item as Ident);
+    vrf_state_struct(VrfStateInput::new(struct_name, VrfStateArgs::default())).into()
+}
+
+/// Same as [`declare_vrf_state`], wrapping a [`VrfCompactAccountData`]
+/// whose account is sized with [`account_size_for`].
+///
+/// Example
+/// ```ignore
+/// use anchor_lang::prelude::*;
+///
+/// declare_id!("6trpiXViFkrXFR1F1nMDGMyigUo89c53La2Bpc4mMwyG");
+/// vrf_sdk::declare_compact_vrf_state!(VrfState);
+/// ```
+#[proc_macro]
+pub fn declare_compact_vrf_state(item: TokenStream) -> TokenStream {
+    let struct_name = syn::parse_macro_input!(item as Ident);
+    let args = VrfStateArgs {
+        compact: true,
+        ..Default::default()
+    };
+    vrf_state_struct(VrfStateInput::new(struct_name, args)).into()
+}
+
+/// Same as [`declare_vrf_state`], storing the program own fields
+/// after the VRF data in the same account.
+///
+/// - `owner = <path>`: the program owning the account, `crate::ID` by default.
+/// - `compact`: wrap a [`VrfCompactAccountData`], the account is sized with [`account_size_for_state`].
+///
+/// The fields must be `Pod`. `AccountSerialize` and `AccountDeserialize` are implemented,
+/// so the state can be used with `Account` as well as `AccountLoader`.
+///
+/// Example
+/// ```ignore
+/// #[vrf_sdk::vrf_state(owner = crate::ID)]
+/// pub struct CoinFlipState {
+///     pub bettor: Pubkey,
+///     pub bet_amount: u64,
+/// }
+///
+/// let state = ctx.accounts.vrf.load()?;
+/// let (bettor, result) = (state.bettor, state.result);
+/// ```
+#[proc_macro_attribute]
+pub fn vrf_state(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as VrfStateArgs);
+    let input = syn::parse_macro_input!(item as DeriveInput);
+
+    let fields = match input.data {
+        Data::Struct(data) => match data.fields {
+            Fields::Named(fields) => fields.named.into_iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(fields) => {
+                return syn::Error::new_spanned(fields, "expected named fields")
+                    .to_compile_error()
+                    .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(input.ident, "expected a struct")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    vrf_state_struct(VrfStateInput {
+        attrs: input.attrs,
+        vis: input.vis,
+        struct_name: input.ident,
+        fields,
+        args,
+    })
+    .into()
+}
+
+struct VrfStateArgs {
+    owner: Path,
+    compact: bool,
+}
+
+impl Default for VrfStateArgs {
+    fn default() -> Self {
+        Self {
+            owner: parse_quote!(crate::ID),
+            compact: false,
+        }
+    }
+}
+
+impl Parse for VrfStateArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self::default();
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            match name.to_string().as_str() {
+                "owner" => {
+                    input.parse::<Token![=]>()?;
+                    args.owner = input.parse()?;
+                }
+                "compact" => args.compact = true,
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "expected `owner = <path>` or `compact`",
+                    ))
+                }
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+struct VrfStateInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    struct_name: Ident,
+    fields: Vec<Field>,
+    args: VrfStateArgs,
+}
+
+impl VrfStateInput {
+    fn new(struct_name: Ident, args: VrfStateArgs) -> Self {
+        Self {
+            attrs: Attribute::parse_outer
+                .parse2(quote! {
+                    /// Wrapper struct for `vrf_sdk` account data
+                    ///
+                    /// Each randomness request should create a new instance of this
+                    /// struct on-chain, and call (request_randomness)[`vrf_sdk::request_randomness`]
+                })
+                .unwrap(),
+            vis: parse_quote!(pub),
+            struct_name,
+            fields: Vec::new(),
+            args,
+        }
+    }
+}
+
+fn vrf_state_struct(input: VrfStateInput) -> proc_macro2::TokenStream {
+    let VrfStateInput {
+        attrs,
+        vis,
+        struct_name,
+        fields,
+        args: VrfStateArgs { owner, compact },
+    } = input;
     let struct_name_str = struct_name.to_string();
+    let field_types = fields.iter().map(|field| &field.ty);
+
+    let account_data: Path = if compact {
+        parse_quote!(::vrf_sdk::compact::VrfCompactAccountData)
+    } else {
+        parse_quote!(::vrf_sdk::vrf::VrfAccountData)
+    };
+    // a compact account must stay smaller than a full one, see `vrf_sdk::compact`
+    let size_check = compact.then(|| {
+        quote! {
+            const _: () = assert!(
+                ::vrf_sdk::compact::account_size(
+                    ::vrf_sdk::vrf::MAX_CALLBACK_ACCOUNTS,
+                    ::vrf_sdk::vrf::MAX_CALLBACK_IX_DATA_LEN,
+                ) + ::std::mem::size_of::<#struct_name>()
+                    - ::std::mem::size_of::<#account_data>()
+                    < ::vrf_sdk::ACCOUNT_SIZE,
+                "vrf_state fields are too large for a compact account"
+            );
+        }
+    });
 
     quote! {
-        /// Wrapper struct for `vrf_sdk::VrfAccountData`
-        ///
-        /// Each randomness request should create a new instance of this
-        /// struct on-chain, and call (request_randomness)[`vrf_sdk::request_randomness`]
+        #(#attrs)*
         #[derive(Clone, Copy)]
-        #[repr(packed)]
-        pub struct #struct_name {
-            vrf: ::vrf_sdk::vrf::VrfAccountData,
+        #[repr(C, packed)]
+        #vis struct #struct_name {
+            vrf: #account_data,
+            #(#fields,)*
         }
 
+        // the program fields are reinterpreted from the account data as well
+        const _: fn() = || {
+            fn assert_pod<T: ::vrf_sdk::__private::Pod>() {}
+            #(assert_pod::<#field_types>();)*
+        };
+
+        #size_check
+
         #[automatically_derived]
         unsafe impl ::vrf_sdk::__private::Pod for #struct_name {}
 
@@ -49,7 +231,7 @@ pub fn declare_vrf_state(item: TokenStream) -> TokenStream {
         #[automatically_derived]
         impl ::vrf_sdk::__private::Owner for #struct_name {
             fn owner() -> ::vrf_sdk::__private::Pubkey {
-                crate::ID
+                #owner
             }
         }
 
@@ -60,7 +242,7 @@ pub fn declare_vrf_state(item: TokenStream) -> TokenStream {
 
         #[automatically_derived]
         impl std::ops::Deref for #struct_name {
-            type Target = vrf_sdk::vrf::VrfAccountData;
+            type Target = #account_data;
 
             fn deref(&self) -> &Self::Target {
                 &self.vrf
@@ -74,27 +256,41 @@ pub fn declare_vrf_state(item: TokenStream) -> TokenStream {
             }
         }
 
-		#[automatically_derived]
-		impl ::vrf_sdk::__private::AccountDeserialize for #struct_name {
-			fn try_deserialize(buf: &mut &[u8]) -> ::vrf_sdk::__private::Result<Self> {
-				if buf.len() < <Self as ::vrf_sdk::__private::Discriminator>::DISCRIMINATOR.len() {
-					return Err(::vrf_sdk::__private::error::ErrorCode::AccountDiscriminatorNotFound.into());
-				}
-				let given_disc = &buf[..8];
-				if &<Self as ::vrf_sdk::__private::Discriminator>::DISCRIMINATOR != given_disc {
-					return Err(::vrf_sdk::__private::error!(::vrf_sdk::__private::error::ErrorCode::AccountDiscriminatorMismatch).with_account_name(#struct_name_str));
-				}
-				Self::try_deserialize_unchecked(buf)
-			}
-
-			fn try_deserialize_unchecked(buf: &mut &[u8]) -> ::vrf_sdk::__private::Result<Self> {
-				let data: &[u8] = &buf[8..];
-				// Re-interpret raw bytes into the POD data structure.
-				let account = ::vrf_sdk::__private::from_bytes(data);
-				// Copy out the bytes into a new, owned data structure.
-				Ok(*account)
-			}
-		}
+        #[automatically_derived]
+        impl ::vrf_sdk::__private::AccountSerialize for #struct_name {
+            fn try_serialize<W: ::std::io::Write>(&self, writer: &mut W) -> ::vrf_sdk::__private::Result<()> {
+                // A compact account callback, after the state, is left untouched
+                writer
+                    .write_all(&<Self as ::vrf_sdk::__private::Discriminator>::DISCRIMINATOR)
+                    .and_then(|_| writer.write_all(::vrf_sdk::__private::bytes_of(self)))
+                    .map_err(|_| ::vrf_sdk::__private::error::ErrorCode::AccountDidNotSerialize.into())
+            }
+        }
+
+        #[automatically_derived]
+        impl ::vrf_sdk::__private::AccountDeserialize for #struct_name {
+            fn try_deserialize(buf: &mut &[u8]) -> ::vrf_sdk::__private::Result<Self> {
+                if buf.len() < <Self as ::vrf_sdk::__private::Discriminator>::DISCRIMINATOR.len() {
+                    return Err(::vrf_sdk::__private::error::ErrorCode::AccountDiscriminatorNotFound.into());
+                }
+                let given_disc = &buf[..8];
+                if &<Self as ::vrf_sdk::__private::Discriminator>::DISCRIMINATOR != given_disc {
+                    return Err(::vrf_sdk::__private::error!(::vrf_sdk::__private::error::ErrorCode::AccountDiscriminatorMismatch).with_account_name(#struct_name_str));
+                }
+                Self::try_deserialize_unchecked(buf)
+            }
+
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> ::vrf_sdk::__private::Result<Self> {
+                if buf.len() < 8 + ::std::mem::size_of::<Self>() {
+                    return Err(::vrf_sdk::__private::error::ErrorCode::AccountDidNotDeserialize.into());
+                }
+                // A compact account store its callback after the state
+                let data: &[u8] = &buf[8..8 + ::std::mem::size_of::<Self>()];
+                // Re-interpret raw bytes into the POD data structure.
+                let account = ::vrf_sdk::__private::from_bytes(data);
+                // Copy out the bytes into a new, owned data structure.
+                Ok(*account)
+            }
+        }
     }
-    .into()
 }
@@ -0,0 +1,398 @@
+//! Compact VRF accounts, sized for their callback.
+//!
+//! A [`VrfAccountData`] reserve room for 32 callback accounts and 1024 bytes of
+//! instruction data, so every request pay the rent of more than 3 KB.
+//! A compact account only store a fixed [`VrfCompactAccountData`] header,
+//! followed by the callback accounts and instruction data:
+//!
+//! ```text
+//! discriminator | VrfCompactAccountData | program fields | [CompactAccountMeta; accounts_len] | [u8; ix_data_len]
+//! ```
+//!
+//! The program fields are the custom fields of a [vrf_state](`crate::vrf_state`), if any.
+//!
+//! It uses the same discriminator as [`VrfAccountData`], the two layouts are told apart
+//! by the account size: a compact account is always smaller than [`ACCOUNT_SIZE`](`crate::ACCOUNT_SIZE`),
+//! see [`VrfAccountView`](`crate::vrf::VrfAccountView`). A request into a compact account allocated
+//! with [`ACCOUNT_SIZE`](`crate::ACCOUNT_SIZE`) bytes or more, e.g. with large program fields,
+//! fails with [`VrfError::InvalidAccountSize`].
+//! `AccountLoader::load` only read the header, so the callback use the account as usual.
+//!
+//! Requests through the [coordinator](`crate::coordinator`) always use the full layout.
+//!
+//! Example
+//! ```ignore
+//! vrf_sdk::declare_compact_vrf_state!(VrfState);
+//!
+//! #[derive(Accounts)]
+//! pub struct RequestRandomness<'info> {
+//!     #[account(
+//! 		init,
+//! 		payer = payer,
+//! 		space = vrf_sdk::account_size_for(
+//! 			&accounts::OnRandomnessResponse { vrf: Pubkey::default() },
+//! 			&instruction::OnRandomnessResponse { vrf_result: Default::default() },
+//! 		),
+//! 		seeds = [
+//! 			// Specify PDA seeds
+//! 		],
+//! 		bump,
+//! 	)]
+//!     vrf: AccountLoader<'info, VrfState>,
+//!     system_program: Program<'info, System>,
+//! }
+//! ```
+
+use std::mem::size_of;
+
+use anchor_lang::{
+    __private::bytemuck, prelude::*, solana_program::instruction::AccountMeta, Discriminator,
+};
+
+use crate::{
+    error::VrfError,
+    vrf::{
+        impl_request_lifecycle, validate_callback_lookup, validate_request, CallbackAccounts,
+        CallbackLookup, CallbackLookupPacked, CallbackOffsets, CallbackRef, VrfAccountData,
+        VrfLastRound, VrfLayout, VrfStatus, VrfStatusHeader, LAYOUT_VERSION, MAX_CALLBACK_ACCOUNTS,
+        MAX_CALLBACK_IX_DATA_LEN, OLDEST_COMPACT_LAYOUT_VERSION, PROOF_BYTE_LEN, RESULT_BYTE_LEN,
+        SEEDS_BYTE_LEN, STATUS_HEADER_VERSION, VRF_RESULT_DISCRIMINATOR,
+    },
+};
+
+/// Size of a compact account without callback, including the discriminator.
+pub const HEADER_SIZE: usize = size_of::<VrfCompactAccountData>() + /* DISCRIMINATOR */ 8;
+
+/// Size of a compact account whose callback has `accounts_len` accounts
+/// and `ix_data_len` bytes of instruction data.
+///
+/// See also [`account_size_for`](`crate::account_size_for`).
+pub const fn account_size(accounts_len: usize, ix_data_len: usize) -> usize {
+    HEADER_SIZE + accounts_len * size_of::<CompactAccountMeta>() + ix_data_len
+}
+
+// compile time assertion that a compact account cannot be mistaken for a full one
+const _: () =
+    assert!(account_size(MAX_CALLBACK_ACCOUNTS, MAX_CALLBACK_IX_DATA_LEN) < crate::ACCOUNT_SIZE);
+
+/// A callback account of a compact account, laid out explicitly
+/// since the order of the [`AccountMetaPacked`](`crate::vrf::AccountMetaPacked`) fields is not.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct CompactAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: u8,
+    pub is_writable: u8,
+}
+
+unsafe impl bytemuck::Pod for CompactAccountMeta {}
+unsafe impl bytemuck::Zeroable for CompactAccountMeta {}
+
+impl From<&AccountMeta> for CompactAccountMeta {
+    fn from(meta: &AccountMeta) -> Self {
+        Self {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer as u8,
+            is_writable: meta.is_writable as u8,
+        }
+    }
+}
+
+impl From<&CompactAccountMeta> for AccountMeta {
+    fn from(meta: &CompactAccountMeta) -> Self {
+        Self {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer != 0,
+            is_writable: meta.is_writable != 0,
+        }
+    }
+}
+
+/// The callback of a compact account, its accounts and instruction data
+/// are stored after the [`VrfCompactAccountData`].
+#[zero_copy]
+#[repr(C, packed)]
+pub struct CompactCallbackPacked {
+    /// Program ID of the callback program being invoked.
+    pub program_id: Pubkey,
+    /// The number of accounts used in the callback.
+    pub accounts_len: u16,
+    /// The number of serialized bytes in the instruction data.
+    pub ix_data_len: u16,
+    /// Offset of the [`VrfResult`](`crate::VrfResult`) in the instruction data plus one,
+    /// see [`CallbackOffsets`].
+    pub result_offset: u16,
+    /// Offset of the [`VrfProof`](`crate::VrfProof`) in the instruction data plus one,
+    /// `0` if the callback does not take the proof.
+    pub proof_offset: u16,
+    /// Size of the program fields stored between the header and the callback.
+    pub fields_len: u16,
+}
+
+/// Same as [`VrfAccountData`], without the fixed size callback.
+#[zero_copy]
+#[repr(C, packed)]
+pub struct VrfCompactAccountData {
+    pub result: crate::VrfResult,
+    pub proof: [u8; PROOF_BYTE_LEN],
+    pub seeds: [u8; SEEDS_BYTE_LEN],
+
+    /// The unix timestamp when the VRF round was opened.
+    pub request_timestamp: i64,
+
+    /// Who requested the randomness, only used by the coordinator program.
+    pub requester: Pubkey,
+
+    /// The [`Oracle`](`crate::oracle::Oracle`) account expected to fulfill the request.
+    pub oracle: Pubkey,
+
+    /// Where the request is in its lifecycle, see [`status`](`Self::status`).
+    pub header: VrfStatusHeader,
+
+    /// The fee paid to the oracle when requesting, see [`Oracle::fee`](`crate::oracle::Oracle::fee`).
+    pub fee_paid: u64,
+
+    /// The callback accounts stored in an address lookup table, if any.
+    pub lookup: CallbackLookupPacked,
+
+    /// [`LAYOUT_VERSION`] when the request was opened, see [`migrate`](`Self::migrate`).
+    pub layout_version: u8,
+
+    /// Number of requests opened with this account, see [`VrfAccountData::round`].
+    pub round: u64,
+
+    /// The previous round, kept when the account is re-armed.
+    pub last_round: VrfLastRound,
+
+    /// Reserved for new fields, so they do not shift the callback, see [`LAYOUT_VERSION`].
+    pub _buf: [u8; 64],
+
+    pub callback: CompactCallbackPacked,
+}
+
+unsafe impl bytemuck::Pod for VrfCompactAccountData {}
+unsafe impl bytemuck::Zeroable for VrfCompactAccountData {}
+
+impl Discriminator for VrfCompactAccountData {
+    const DISCRIMINATOR: [u8; 8] = VrfAccountData::DISCRIMINATOR;
+}
+
+impl VrfCompactAccountData {
+    /// Split the data of a compact account, after the discriminator,
+    /// into the header and the callback data.
+    pub fn split(data: &[u8]) -> anchor_lang::Result<(&Self, &[u8])> {
+        if data.len() < size_of::<Self>() {
+            return Err(ErrorCode::AccountDidNotDeserialize.into());
+        }
+
+        let (header, callback_data) = data.split_at(size_of::<Self>());
+        Ok((bytemuck::from_bytes(header), callback_data))
+    }
+
+    /// Same as [`split`](`Self::split`), mutably.
+    pub fn split_mut(data: &mut [u8]) -> anchor_lang::Result<(&mut Self, &mut [u8])> {
+        if data.len() < size_of::<Self>() {
+            return Err(ErrorCode::AccountDidNotDeserialize.into());
+        }
+
+        let (header, callback_data) = data.split_at_mut(size_of::<Self>());
+        Ok((bytemuck::from_bytes_mut(header), callback_data))
+    }
+
+    /// Fill in the seeds and callback of a new randomness request,
+    /// `callback_data` being the account data following the header.
+    ///
+    /// This does NOT emit [`VrfRequestRandomness`](`crate::vrf::VrfRequestRandomness`),
+    /// use [request_randomness](`crate::request_randomness`) instead.
+    pub fn set_request(
+        &mut self,
+        callback_data: &mut [u8],
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+    ) -> anchor_lang::Result<()> {
+        self.set_request_at(
+            callback_data,
+            seeds,
+            oracle,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            None,
+        )
+    }
+
+    /// Same as [`set_request`](`Self::set_request`), with the given callback `offsets`,
+    /// found in `callback_ix_data` if `None`, see [`CallbackOffsets`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_request_at(
+        &mut self,
+        callback_data: &mut [u8],
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        offsets: Option<CallbackOffsets>,
+    ) -> anchor_lang::Result<()> {
+        validate_request(seeds, callback_accounts, callback_ix_data)?;
+        let offsets = match offsets {
+            Some(offsets) => offsets,
+            None => CallbackOffsets::find(callback_ix_data)?,
+        };
+
+        self.open_request(seeds, oracle)?;
+        self.set_callback_at(
+            callback_data,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            offsets,
+        )
+    }
+
+    /// Store the callback in `callback_data`, which must hold at least
+    /// `account_size(callback_accounts.len(), callback_ix_data.len()) - HEADER_SIZE` bytes.
+    pub fn set_callback(
+        &mut self,
+        callback_data: &mut [u8],
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+    ) -> anchor_lang::Result<()> {
+        let offsets = CallbackOffsets::find(callback_ix_data)?;
+        self.set_callback_at(
+            callback_data,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            offsets,
+        )
+    }
+
+    /// Same as [`set_callback`](`Self::set_callback`), with the given callback `offsets`.
+    pub fn set_callback_at(
+        &mut self,
+        callback_data: &mut [u8],
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        offsets: CallbackOffsets,
+    ) -> anchor_lang::Result<()> {
+        offsets.validate(callback_ix_data.len())?;
+        let (result_offset, proof_offset) = offsets.packed();
+
+        let accounts_size = callback_accounts.len() * size_of::<CompactAccountMeta>();
+        if callback_data.len() < accounts_size + callback_ix_data.len() {
+            return Err(error!(ErrorCode::AccountDidNotSerialize)
+                .with_values((callback_data.len(), accounts_size + callback_ix_data.len())));
+        }
+
+        let (accounts, ix_data) = callback_data.split_at_mut(accounts_size);
+        for (packed, meta) in bytemuck::cast_slice_mut::<u8, CompactAccountMeta>(accounts)
+            .iter_mut()
+            .zip(callback_accounts)
+        {
+            *packed = CompactAccountMeta::from(meta);
+        }
+        ix_data[0..callback_ix_data.len()].copy_from_slice(callback_ix_data);
+
+        self.callback = CompactCallbackPacked {
+            program_id: callback_program_id,
+            accounts_len: callback_accounts.len() as u16,
+            ix_data_len: callback_ix_data.len() as u16,
+            result_offset,
+            proof_offset,
+            fields_len: 0,
+        };
+
+        Ok(())
+    }
+
+    /// The stored callback, `data` being the account data following the header.
+    pub fn callback<'a>(&self, data: &'a [u8]) -> anchor_lang::Result<CallbackRef<'a>> {
+        let callback = self.callback;
+        let accounts_start = callback.fields_len as usize;
+        let ix_data_start =
+            accounts_start + callback.accounts_len as usize * size_of::<CompactAccountMeta>();
+        let ix_data_end = ix_data_start + callback.ix_data_len as usize;
+        if data.len() < ix_data_end {
+            return Err(
+                error!(ErrorCode::AccountDidNotDeserialize).with_values((data.len(), ix_data_end))
+            );
+        }
+
+        Ok(CallbackRef::new(
+            callback.program_id,
+            CallbackAccounts::Compact(bytemuck::cast_slice(&data[accounts_start..ix_data_start])),
+            &data[ix_data_start..ix_data_end],
+            callback.result_offset,
+            callback.proof_offset,
+        ))
+    }
+}
+
+impl_request_lifecycle!(VrfCompactAccountData, OLDEST_COMPACT_LAYOUT_VERSION);
+
+impl VrfLayout for VrfCompactAccountData {
+    fn init_request(
+        &mut self,
+        callback_data: &mut [u8],
+        fields_len: usize,
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        callback_offsets: Option<CallbackOffsets>,
+        lookup: Option<&CallbackLookup>,
+        fee_paid: u64,
+    ) -> anchor_lang::Result<()> {
+        // the layouts are told apart by the account size
+        let account_len = HEADER_SIZE + fields_len + callback_data.len();
+        if account_len >= crate::ACCOUNT_SIZE {
+            return Err(error!(VrfError::InvalidAccountSize)
+                .with_values((account_len, crate::ACCOUNT_SIZE)));
+        }
+
+        self.set_request_at(
+            callback_data,
+            seeds,
+            oracle,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            callback_offsets,
+        )?;
+        self.callback.fields_len = fields_len as u16;
+        if let Some(lookup) = lookup {
+            self.set_callback_lookup(lookup)?;
+        }
+        self.fee_paid = fee_paid;
+
+        Ok(())
+    }
+
+    fn rearm_request(&mut self) -> anchor_lang::Result<()> {
+        self.rearm()
+    }
+
+    fn round(&self) -> u64 {
+        self.round
+    }
+
+    fn set_batch(&mut self, len: u8) -> anchor_lang::Result<()> {
+        // no room for the batch results
+        Err(error!(VrfError::InvalidBatch).with_values((len, 0)))
+    }
+
+    fn cancel_request(&mut self, timeout: i64) -> anchor_lang::Result<()> {
+        self.cancel(timeout)
+    }
+
+    fn migrate_layout(&mut self) -> anchor_lang::Result<u8> {
+        self.migrate()
+    }
+}
@@ -0,0 +1,444 @@
+//! Client side of the `vrf-coordinator` program.
+//!
+//! The coordinator host the [oracle registry](`crate::oracle`).
+//!
+//! Instead of invoking the requesting program directly, the VRF-server can fulfill
+//! a request through the coordinator. The coordinator verify the proof, write the
+//! `result` and `proof` into the [`VrfAccountData`] and then invoke the stored callback,
+//! signed by the coordinator [`authority`].
+//!
+//! A request is signed by the [`requester`] PDA of the requesting program, and the coordinator
+//! only accepts a callback to that same program. The coordinator authority is the only signer
+//! of the callback, the accounts stored as signers are passed as non-signers.
+//!
+//! So the callback must call [`check_caller`], which check both that it was invoked by the coordinator
+//! and that the `vrf` account was requested by this program, and must not trust any other signer.
+//!
+//! Example
+//! ```ignore
+//! #[derive(Accounts)]
+//! pub struct RequestRandomness<'info> {
+//!     #[account(mut)]
+//!     user: Signer<'info>,
+//!     #[account(
+//! 		init,
+//! 		payer = user,
+//! 		space = vrf_sdk::ACCOUNT_SIZE,
+//! 		owner = vrf_sdk::coordinator::ID,
+//! 		seeds = [
+//! 			// Specify PDA seeds
+//! 		],
+//! 		bump,
+//! 	)]
+//!     vrf: AccountLoader<'info, vrf_sdk::coordinator::VrfState>,
+//!     /// CHECK: checked by the coordinator
+//!     oracle: AccountInfo<'info>,
+//!     /// CHECK: signed by `request_randomness`
+//!     #[account(address = vrf_sdk::coordinator::requester(&crate::ID))]
+//!     vrf_requester: AccountInfo<'info>,
+//!     /// CHECK:
+//!     #[account(address = vrf_sdk::coordinator::ID)]
+//!     coordinator: AccountInfo<'info>,
+//!     system_program: Program<'info, System>,
+//! }
+//!
+//! #[derive(Accounts)]
+//! pub struct OnRandomnessResponse<'info> {
+//!     coordinator_authority: Signer<'info>,
+//!     vrf: AccountLoader<'info, vrf_sdk::coordinator::VrfState>,
+//! }
+//!
+//! pub fn on_randomness_response(ctx: Context<OnRandomnessResponse>, vrf_result: vrf_sdk::VrfResult) -> Result<()> {
+//!     vrf_sdk::coordinator::check_caller(
+//!         &ctx.accounts.coordinator_authority,
+//!         &ctx.accounts.vrf.load()?,
+//!         ctx.program_id,
+//!     )?;
+//!     // ...
+//! }
+//! ```
+
+use std::ops::{Deref, DerefMut};
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+    },
+    Discriminator, InstructionData, ToAccountMetas,
+};
+
+use crate::{
+    oracle::FeePayer,
+    vrf::{AccountMetaPacked, CallbackLookup, VrfAccountData},
+};
+
+anchor_lang::declare_id!("Ak7CBLEMGKuqAbiVpCV5iV9BmVBAv5ZC7hdawe72DBuw");
+
+pub const AUTHORITY_SEED: &[u8] = b"authority";
+
+/// Seed of the [`requester`] PDA, derived from the requesting program.
+pub const REQUESTER_SEED: &[u8] = b"vrf-requester";
+
+/// Seconds after which the requester can cancel a request not yet fulfilled.
+pub const REQUEST_TIMEOUT: i64 = 10 * 60;
+
+/// Instruction discriminators of the coordinator program.
+pub mod instruction {
+    pub const REQUEST: [u8; 8] = [46, 101, 67, 11, 76, 137, 12, 173];
+    pub const REQUEST_WITH_LOOKUP: [u8; 8] = [9, 222, 220, 11, 175, 92, 91, 62];
+    pub const FULFILL: [u8; 8] = [143, 2, 52, 206, 174, 164, 247, 72];
+    pub const CANCEL: [u8; 8] = [232, 219, 223, 41, 219, 236, 220, 190];
+    pub const CLOSE: [u8; 8] = [98, 165, 201, 177, 108, 65, 206, 96];
+    pub const SET_ORACLE_FEE: [u8; 8] = [8, 150, 163, 122, 75, 111, 117, 55];
+    pub const REGISTER_ORACLE: [u8; 8] = [176, 200, 234, 37, 199, 129, 164, 111];
+    pub const SET_ORACLE_STATUS: [u8; 8] = [53, 114, 250, 161, 104, 108, 76, 40];
+    pub const MIGRATE: [u8; 8] = [155, 234, 231, 146, 236, 158, 162, 30];
+}
+
+/// The PDA signing every callback invoked by the coordinator.
+pub fn authority() -> Pubkey {
+    Pubkey::find_program_address(&[AUTHORITY_SEED], &ID).0
+}
+
+/// The PDA of `program_id` signing its requests, and recorded as their requester.
+pub fn requester(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REQUESTER_SEED], program_id).0
+}
+
+/// Ensure the callback was invoked by the coordinator, for a request of `program_id`.
+///
+/// `authority` must be the coordinator [`authority`] and must be a signer,
+/// `vrf` must have been requested by the [`requester`] of `program_id`.
+pub fn check_caller(
+    authority: &AccountInfo<'_>,
+    vrf: &VrfAccountData,
+    program_id: &Pubkey,
+) -> anchor_lang::Result<()> {
+    if !authority.is_signer {
+        return Err(error!(ErrorCode::AccountNotSigner).with_account_name("coordinator_authority"));
+    }
+
+    if authority.key() != self::authority() {
+        return Err(error!(ErrorCode::ConstraintAddress)
+            .with_account_name("coordinator_authority")
+            .with_pubkeys((authority.key(), self::authority())));
+    }
+
+    let requester = vrf.requester;
+    if requester != self::requester(program_id) {
+        return Err(error!(ErrorCode::ConstraintAddress)
+            .with_account_name("vrf")
+            .with_pubkeys((requester, self::requester(program_id))));
+    }
+
+    Ok(())
+}
+
+/// Request a new randomness value through the coordinator.
+///
+/// The `vrf` account must be created with `owner = vrf_sdk::coordinator::ID`
+/// and should be created seperately for each request.
+/// `requester` is the [`requester`] PDA of `program_id`, the calling program, signed here.
+/// The callback is invoked on `program_id`, which can close the `vrf` account once fulfilled,
+/// see [`close_request`], or cancel the request after [`REQUEST_TIMEOUT`], see [`cancel_request`].
+///
+/// `fee_payer` pay the [`Oracle::fee`](`crate::oracle::Oracle::fee`), if any.
+///
+/// Example
+/// ```ignore
+/// vrf_sdk::coordinator::request_randomness(
+/// 	&seeds,
+/// 	&ctx.accounts.vrf,
+/// 	&ctx.accounts.oracle,
+/// 	&ctx.accounts.vrf_requester,
+/// 	&ctx.accounts.coordinator,
+/// 	None,
+/// 	ctx.program_id,
+/// 	accounts::OnRandomnessResponse {
+/// 		coordinator_authority: vrf_sdk::coordinator::authority(),
+/// 		vrf: ctx.accounts.vrf.key(),
+/// 	},
+/// 	instruction::OnRandomnessResponse {
+/// 		vrf_result: Default::default(),
+/// 	},
+/// )?;
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn request_randomness<'info, CB, IX>(
+    seeds: &[u8],
+    vrf: &AccountLoader<'info, VrfState>,
+    oracle: &AccountInfo<'info>,
+    requester: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    fee_payer: Option<&FeePayer<'_, 'info>>,
+    program_id: &Pubkey,
+    callback: CB,
+    callback_ix_data: IX,
+) -> anchor_lang::Result<()>
+where
+    CB: ToAccountMetas,
+    IX: InstructionData,
+{
+    invoke_request(
+        seeds,
+        vrf,
+        oracle,
+        requester,
+        coordinator_program,
+        fee_payer,
+        program_id,
+        callback.to_account_metas(None),
+        callback_ix_data.data(),
+        None,
+    )
+}
+
+/// Same as [`request_randomness`], appending the accounts of an address lookup table
+/// to the callback accounts, see [`CallbackLookup`].
+#[allow(clippy::too_many_arguments)]
+pub fn request_randomness_with_lookup<'info, CB, IX>(
+    seeds: &[u8],
+    vrf: &AccountLoader<'info, VrfState>,
+    oracle: &AccountInfo<'info>,
+    requester: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    fee_payer: Option<&FeePayer<'_, 'info>>,
+    program_id: &Pubkey,
+    callback: CB,
+    callback_ix_data: IX,
+    lookup: &CallbackLookup,
+) -> anchor_lang::Result<()>
+where
+    CB: ToAccountMetas,
+    IX: InstructionData,
+{
+    invoke_request(
+        seeds,
+        vrf,
+        oracle,
+        requester,
+        coordinator_program,
+        fee_payer,
+        program_id,
+        callback.to_account_metas(None),
+        callback_ix_data.data(),
+        Some(lookup),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn invoke_request<'info>(
+    seeds: &[u8],
+    vrf: &AccountLoader<'info, VrfState>,
+    oracle: &AccountInfo<'info>,
+    requester: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    fee_payer: Option<&FeePayer<'_, 'info>>,
+    program_id: &Pubkey,
+    callback_accounts: Vec<AccountMeta>,
+    callback_ix_data: Vec<u8>,
+    lookup: Option<&CallbackLookup>,
+) -> anchor_lang::Result<()> {
+    let callback_accounts = callback_accounts
+        .into_iter()
+        .map(|meta| AccountMetaPacked {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        })
+        .collect::<Vec<_>>();
+
+    let mut data = match lookup {
+        Some(_) => instruction::REQUEST_WITH_LOOKUP.to_vec(),
+        None => instruction::REQUEST.to_vec(),
+    };
+    (
+        seeds.to_vec(),
+        *program_id,
+        callback_accounts,
+        callback_ix_data,
+    )
+        .serialize(&mut data)?;
+    if let Some(lookup) = lookup {
+        lookup.serialize(&mut data)?;
+    }
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(requester.key(), true),
+        AccountMeta::new_readonly(oracle.key(), false),
+        AccountMeta::new(vrf.key(), false),
+    ];
+    let mut account_infos = vec![
+        requester.clone(),
+        oracle.clone(),
+        vrf.to_account_info(),
+        coordinator_program.clone(),
+    ];
+    // the fee accounts are passed as remaining accounts
+    if let Some(fee_payer) = fee_payer {
+        accounts.extend(fee_payer.to_account_metas());
+        account_infos.extend(fee_payer.to_account_infos());
+    }
+
+    let ix = Instruction {
+        program_id: ID,
+        accounts,
+        data,
+    };
+
+    invoke_signed(
+        &ix,
+        &account_infos,
+        &[&[REQUESTER_SEED, &[requester_bump(program_id)]]],
+    )?;
+
+    Ok(())
+}
+
+fn requester_bump(program_id: &Pubkey) -> u8 {
+    Pubkey::find_program_address(&[REQUESTER_SEED], program_id).1
+}
+
+/// Cancel a request of `program_id` once [`REQUEST_TIMEOUT`] has elapsed, closing `vrf` to `receiver`.
+pub fn cancel_request<'info>(
+    vrf: &AccountLoader<'info, VrfState>,
+    requester: &AccountInfo<'info>,
+    receiver: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> anchor_lang::Result<()> {
+    invoke_close(
+        instruction::CANCEL,
+        vrf,
+        requester,
+        receiver,
+        coordinator_program,
+        program_id,
+    )
+}
+
+/// Close the fulfilled `vrf` account of a request of `program_id` to `receiver`.
+pub fn close_request<'info>(
+    vrf: &AccountLoader<'info, VrfState>,
+    requester: &AccountInfo<'info>,
+    receiver: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> anchor_lang::Result<()> {
+    invoke_close(
+        instruction::CLOSE,
+        vrf,
+        requester,
+        receiver,
+        coordinator_program,
+        program_id,
+    )
+}
+
+fn invoke_close<'info>(
+    discriminator: [u8; 8],
+    vrf: &AccountLoader<'info, VrfState>,
+    requester: &AccountInfo<'info>,
+    receiver: &AccountInfo<'info>,
+    coordinator_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> anchor_lang::Result<()> {
+    let ix = Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new_readonly(requester.key(), true),
+            AccountMeta::new(receiver.key(), false),
+            AccountMeta::new(vrf.key(), false),
+        ],
+        data: discriminator.to_vec(),
+    };
+
+    invoke_signed(
+        &ix,
+        &[
+            requester.clone(),
+            receiver.clone(),
+            vrf.to_account_info(),
+            coordinator_program.clone(),
+        ],
+        &[&[REQUESTER_SEED, &[requester_bump(program_id)]]],
+    )?;
+
+    Ok(())
+}
+
+/// The coordinator instruction upgrading `vrf` to [`LAYOUT_VERSION`](`crate::vrf::LAYOUT_VERSION`),
+/// anyone can send it.
+///
+/// See also [migrate_account](`crate::migrate_account`).
+pub fn migrate_instruction(vrf: Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: vec![AccountMeta::new(vrf, false)],
+        data: instruction::MIGRATE.to_vec(),
+    }
+}
+
+/// [`VrfAccountData`] owned by the coordinator program.
+///
+/// This is the same as the struct generated by [declare_vrf_state](`crate::declare_vrf_state`)
+/// but with the coordinator as the owner.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct VrfState {
+    vrf: VrfAccountData,
+}
+
+unsafe impl anchor_lang::__private::bytemuck::Pod for VrfState {}
+unsafe impl anchor_lang::__private::bytemuck::Zeroable for VrfState {}
+
+impl anchor_lang::ZeroCopy for VrfState {}
+
+impl anchor_lang::Owner for VrfState {
+    fn owner() -> Pubkey {
+        ID
+    }
+}
+
+impl Discriminator for VrfState {
+    const DISCRIMINATOR: [u8; 8] = VrfAccountData::DISCRIMINATOR;
+}
+
+impl Deref for VrfState {
+    type Target = VrfAccountData;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vrf
+    }
+}
+
+impl DerefMut for VrfState {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vrf
+    }
+}
+
+impl anchor_lang::AccountDeserialize for VrfState {
+    fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        if buf.len() < Self::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        let given_disc = &buf[..8];
+        if Self::DISCRIMINATOR != given_disc {
+            return Err(
+                error!(ErrorCode::AccountDiscriminatorMismatch).with_account_name("VrfState")
+            );
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let data: &[u8] = &buf[8..];
+        // Re-interpret raw bytes into the POD data structure.
+        let account = anchor_lang::__private::bytemuck::from_bytes(data);
+        // Copy out the bytes into a new, owned data structure.
+        Ok(*account)
+    }
+}
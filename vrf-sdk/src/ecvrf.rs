@@ -0,0 +1,177 @@
+//! Pure-Rust verifier for `ECVRF-SECP256K1-SHA256-TAI`.
+//!
+//! This is the cipher suite used by `vrf-server` (via the `vrf` crate), implemented
+//! as described in [VRF-draft-05](https://datatracker.ietf.org/doc/pdf/draft-irtf-cfrg-vrf-05).
+//!
+//! Unlike the `vrf` crate this does not depend on OpenSSL, so it can be used on-chain.
+//! Hashing goes through [`solana_program::hash`] which is a syscall when running on BPF.
+//!
+//! Note: verifying a proof performs 4 scalar multiplications on secp256k1,
+//! so the calling instruction should request a larger compute budget.
+
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use k256::{
+    elliptic_curve::{
+        sec1::{FromEncodedPoint, ToEncodedPoint},
+        PrimeField,
+    },
+    AffinePoint, EncodedPoint, FieldBytes, ProjectivePoint, Scalar,
+};
+use solana_program::hash::hashv;
+
+use crate::vrf::{PROOF_BYTE_LEN, RESULT_BYTE_LEN};
+
+/// Suite string of `ECVRF-SECP256K1-SHA256-TAI`.
+pub const SUITE_STRING: u8 = 0xFE;
+
+/// Length of a SEC1 compressed secp256k1 public key.
+pub const PUBLIC_KEY_BYTE_LEN: usize = 33;
+
+const GAMMA_BYTE_LEN: usize = 33;
+const C_BYTE_LEN: usize = 16;
+const S_BYTE_LEN: usize = 32;
+
+// compile time assertion that the proof is exactly `Gamma || c || s`
+const _: [(); GAMMA_BYTE_LEN + C_BYTE_LEN + S_BYTE_LEN] = [(); PROOF_BYTE_LEN];
+
+/// A SEC1 compressed secp256k1 public key, usable in account and instruction data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VrfPublicKey(pub [u8; PUBLIC_KEY_BYTE_LEN]);
+
+// borsh only implements arrays of a few fixed sizes, so we (de)serialize the key by hand
+impl AnchorSerialize for VrfPublicKey {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.0)
+    }
+}
+
+impl AnchorDeserialize for VrfPublicKey {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        if buf.len() < PUBLIC_KEY_BYTE_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Unexpected length of input",
+            ));
+        }
+
+        let mut key = [0u8; PUBLIC_KEY_BYTE_LEN];
+        key.copy_from_slice(&buf[..PUBLIC_KEY_BYTE_LEN]);
+        *buf = &buf[PUBLIC_KEY_BYTE_LEN..];
+        Ok(Self(key))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcvrfError {
+    /// The public key is not a valid compressed secp256k1 point.
+    InvalidPublicKey,
+    /// The proof cannot be decoded into `(Gamma, c, s)`.
+    InvalidProofEncoding,
+    /// No valid point were found while hashing the seeds to the curve.
+    HashToCurve,
+    /// The proof is well formed but does not match the public key and seeds.
+    InvalidProof,
+}
+
+/// Verify `proof` was generated from `alpha` (the seeds) with the secret key of `public_key`.
+///
+/// Return the VRF output hash (the random value) on success.
+pub fn verify(
+    public_key: &[u8; PUBLIC_KEY_BYTE_LEN],
+    proof: &[u8; PROOF_BYTE_LEN],
+    alpha: &[u8],
+) -> Result<[u8; RESULT_BYTE_LEN], EcvrfError> {
+    let y = decode_point(public_key).ok_or(EcvrfError::InvalidPublicKey)?;
+    let (gamma, c, s) = decode_proof(proof)?;
+
+    let h = hash_to_curve_try_and_increment(public_key, alpha)?;
+
+    // U = s*B - c*Y
+    let u = ProjectivePoint::GENERATOR * s - ProjectivePoint::from(y) * c;
+    // V = s*H - c*Gamma
+    let v = ProjectivePoint::from(h) * s - ProjectivePoint::from(gamma) * c;
+
+    let derived_c = hash_points(&[ProjectivePoint::from(h), ProjectivePoint::from(gamma), u, v]);
+
+    if derived_c != c {
+        return Err(EcvrfError::InvalidProof);
+    }
+
+    Ok(gamma_to_hash(&gamma))
+}
+
+/// Compute the VRF output hash from a proof, WITHOUT verifying it.
+pub fn proof_to_hash(proof: &[u8; PROOF_BYTE_LEN]) -> Result<[u8; RESULT_BYTE_LEN], EcvrfError> {
+    let (gamma, _, _) = decode_proof(proof)?;
+    Ok(gamma_to_hash(&gamma))
+}
+
+fn gamma_to_hash(gamma: &AffinePoint) -> [u8; RESULT_BYTE_LEN] {
+    // secp256k1 cofactor is 1, so cofactor * Gamma == Gamma
+    let gamma = gamma.to_encoded_point(true);
+    hashv(&[&[SUITE_STRING, 0x03], gamma.as_bytes()]).to_bytes()
+}
+
+fn decode_point(bytes: &[u8]) -> Option<AffinePoint> {
+    let encoded = EncodedPoint::from_bytes(bytes).ok()?;
+    if !encoded.is_compressed() {
+        return None;
+    }
+
+    Option::from(AffinePoint::from_encoded_point(&encoded))
+}
+
+fn decode_scalar(bytes: &[u8]) -> Option<Scalar> {
+    // left pad to 32 bytes big-endian
+    let mut repr = FieldBytes::default();
+    repr[S_BYTE_LEN - bytes.len()..].copy_from_slice(bytes);
+    Option::from(Scalar::from_repr(repr))
+}
+
+fn decode_proof(proof: &[u8; PROOF_BYTE_LEN]) -> Result<(AffinePoint, Scalar, Scalar), EcvrfError> {
+    let (gamma, rest) = proof.split_at(GAMMA_BYTE_LEN);
+    let (c, s) = rest.split_at(C_BYTE_LEN);
+
+    let gamma = decode_point(gamma).ok_or(EcvrfError::InvalidProofEncoding)?;
+    let c = decode_scalar(c).ok_or(EcvrfError::InvalidProofEncoding)?;
+    let s = decode_scalar(s).ok_or(EcvrfError::InvalidProofEncoding)?;
+
+    Ok((gamma, c, s))
+}
+
+/// `ECVRF_hash_to_curve_try_and_increment` (section 5.4.1.1)
+fn hash_to_curve_try_and_increment(
+    public_key: &[u8; PUBLIC_KEY_BYTE_LEN],
+    alpha: &[u8],
+) -> Result<AffinePoint, EcvrfError> {
+    let mut candidate = [0u8; 1 + RESULT_BYTE_LEN];
+    // arbitrary_string_to_point: interpret the hash as a compressed point with even y
+    candidate[0] = 0x02;
+
+    for ctr in 0..=u8::MAX {
+        let hash = hashv(&[&[SUITE_STRING, 0x01], public_key, alpha, &[ctr]]);
+        candidate[1..].copy_from_slice(hash.as_ref());
+
+        if let Some(point) = decode_point(&candidate) {
+            return Ok(point);
+        }
+    }
+
+    Err(EcvrfError::HashToCurve)
+}
+
+/// `ECVRF_hash_points` (section 5.4.3), truncated to the first 16 bytes
+fn hash_points(points: &[ProjectivePoint; 4]) -> Scalar {
+    let encoded = points
+        .iter()
+        .map(|point| point.to_affine().to_encoded_point(true))
+        .collect::<Vec<_>>();
+
+    let mut input: Vec<&[u8]> = Vec::with_capacity(points.len() + 1);
+    input.push(&[SUITE_STRING, 0x02]);
+    input.extend(encoded.iter().map(|point| point.as_bytes()));
+
+    let hash = hashv(&input);
+    // a 16 bytes value is always lower than the curve order, so unwrap is ok
+    decode_scalar(&hash.as_ref()[..C_BYTE_LEN]).unwrap()
+}
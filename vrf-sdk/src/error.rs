@@ -0,0 +1,120 @@
+//! Errors returned by the sdk.
+//!
+//! The codes `7777..8000` are reserved to [`VrfError`], so they never collide with
+//! the anchor framework errors (`< 6000`) nor with the errors of the requesting program
+//! (`6000..`, unless the program set its own offset).
+//! The codes are stable: a new error is always appended at the end of the enum.
+//!
+//! The errors are also published in the anchor IDL format in `vrf-sdk/idl/errors.json`,
+//! to be merged into the `errors` of the requesting program IDL.
+
+use anchor_lang::prelude::*;
+
+/// First error code of [`VrfError`].
+pub const VRF_ERROR_CODE_OFFSET: u32 = 7777;
+/// End (exclusive) of the error codes reserved to [`VrfError`].
+pub const VRF_ERROR_CODE_END: u32 = 8000;
+
+#[error_code(offset = 7777)]
+#[derive(PartialEq, Eq)]
+pub enum VrfError {
+    /// The [`VrfResult`](`crate::VrfResult`) has not been supplied by the oracle yet.
+    #[msg("The vrf request is not fulfilled yet")]
+    NotFulfilled,
+    #[msg("Vrf proof verification failed")]
+    InvalidProof,
+    #[msg("The vrf oracle is not active")]
+    OracleDisabled,
+    /// The start of the range is greater than its end.
+    #[msg("Random value requested from an empty range")]
+    InvalidRange,
+    /// The weights are empty or all zero.
+    #[msg("Weighted choice without any positive weight")]
+    InvalidWeights,
+    /// The probability is greater than 10_000 basis points.
+    #[msg("Probability above 10_000 basis points")]
+    InvalidProbability,
+    /// More items are requested than available.
+    #[msg("Sample larger than the population")]
+    SampleTooLarge,
+    #[msg("Invalid vrf request status")]
+    InvalidStatus,
+    #[msg("The vrf request is not expired yet")]
+    RequestNotExpired,
+    #[msg("The vrf oracle fee is not paid")]
+    FeeNotPaid,
+    /// The callback instruction data does not hold exactly one [`VrfResult`](`crate::VrfResult`)
+    /// and at most one [`VrfProof`](`crate::VrfProof`).
+    #[msg("Invalid vrf callback instruction data")]
+    InvalidCallback,
+    #[msg("Vrf seeds is longer than 32 bytes")]
+    SeedsTooLong,
+    #[msg("The vrf callback has more than 32 accounts")]
+    TooManyCallbackAccounts,
+    #[msg("The vrf callback instruction data is larger than 1024 bytes")]
+    CallbackDataTooLarge,
+    #[msg("Duplicated vrf callback account")]
+    DuplicateCallbackAccount,
+    #[msg("Vrf seeds is zeroed or empty")]
+    InvalidSeeds,
+    #[msg("Invalid vrf callback address lookup table")]
+    InvalidLookupTable,
+    /// The account was written by a newer sdk, see [`LAYOUT_VERSION`](`crate::vrf::LAYOUT_VERSION`).
+    #[msg("Unsupported vrf account layout version")]
+    UnsupportedLayoutVersion,
+    /// A batch has `1..=MAX_BATCH_LEN` slots and needs a full VRF account,
+    /// see [`MAX_BATCH_LEN`](`crate::vrf::MAX_BATCH_LEN`).
+    #[msg("Invalid vrf batch length")]
+    InvalidBatch,
+    /// The compact account was written before [`OLDEST_COMPACT_LAYOUT_VERSION`](`crate::vrf::OLDEST_COMPACT_LAYOUT_VERSION`),
+    /// it must be closed and requested again.
+    #[msg("Outdated vrf account layout version")]
+    OutdatedLayoutVersion,
+    /// A compact account of [`ACCOUNT_SIZE`](`crate::ACCOUNT_SIZE`) bytes or more would be read as a full one.
+    #[msg("Compact vrf account too large")]
+    InvalidAccountSize,
+    /// More items to shuffle or sample than [`MAX_SAMPLE_LEN`](`crate::random::MAX_SAMPLE_LEN`).
+    #[msg("More than 256 items to shuffle or sample")]
+    TooManyItems,
+    /// The [`VrfResult`](`crate::VrfResult`) or [`VrfProof`](`crate::VrfProof`) of the callback
+    /// does not fit in its instruction data, or they overlap, see [`CallbackOffsets`](`crate::vrf::CallbackOffsets`).
+    #[msg("Invalid vrf result or proof offset in the callback instruction data")]
+    InvalidCallbackOffset,
+}
+
+impl VrfError {
+    /// Every error, in code order.
+    pub const ALL: [VrfError; 23] = [
+        VrfError::NotFulfilled,
+        VrfError::InvalidProof,
+        VrfError::OracleDisabled,
+        VrfError::InvalidRange,
+        VrfError::InvalidWeights,
+        VrfError::InvalidProbability,
+        VrfError::SampleTooLarge,
+        VrfError::InvalidStatus,
+        VrfError::RequestNotExpired,
+        VrfError::FeeNotPaid,
+        VrfError::InvalidCallback,
+        VrfError::SeedsTooLong,
+        VrfError::TooManyCallbackAccounts,
+        VrfError::CallbackDataTooLarge,
+        VrfError::DuplicateCallbackAccount,
+        VrfError::InvalidSeeds,
+        VrfError::InvalidLookupTable,
+        VrfError::UnsupportedLayoutVersion,
+        VrfError::InvalidBatch,
+        VrfError::OutdatedLayoutVersion,
+        VrfError::InvalidAccountSize,
+        VrfError::TooManyItems,
+        VrfError::InvalidCallbackOffset,
+    ];
+
+    /// The error of the given code, if it is a [`VrfError`].
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|err| u32::from(*err) == code)
+    }
+}
+
+// compile time assertion that the codes fit in the reserved range
+const _: () = assert!(VRF_ERROR_CODE_OFFSET + (VrfError::ALL.len() as u32) <= VRF_ERROR_CODE_END);
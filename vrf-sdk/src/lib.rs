@@ -1,18 +1,29 @@
-use std::ops::{DerefMut, RangeInclusive};
+use std::ops::DerefMut;
 
 use anchor_lang::{prelude::*, InstructionData, ToAccountMetas, ZeroCopy};
-use num_traits::{AsPrimitive, PrimInt};
+use vrf::VrfLayout;
 
+pub mod compact;
+pub mod coordinator;
+pub mod ecvrf;
+pub mod error;
+#[cfg(feature = "native")]
+pub mod native;
+pub mod oracle;
+pub mod random;
+pub mod request;
 pub mod vrf;
-pub use vrf_sdk_macro::declare_vrf_state;
+pub use error::VrfError;
+pub use request::VrfRequestBuilder;
+pub use vrf_sdk_macro::{declare_compact_vrf_state, declare_vrf_state, vrf_state};
 
 /// Hidden, to be used by proc-macro declare_vrf_state
 #[doc(hidden)]
 pub mod __private {
     pub use anchor_lang::{
         prelude::Pubkey,
-        AccountDeserialize, Discriminator, Owner, ZeroCopy,
-        __private::bytemuck::{from_bytes, Pod, Zeroable},
+        AccountDeserialize, AccountSerialize, Discriminator, Owner, ZeroCopy,
+        __private::bytemuck::{bytes_of, from_bytes, Pod, Zeroable},
         error, Result,
     };
 }
@@ -38,14 +49,65 @@ pub mod __private {
 /// ```
 pub const ACCOUNT_SIZE: usize = std::mem::size_of::<vrf::VrfAccountData>() + /* DISCRIMINATOR */ 8;
 
+/// Account size of a [compact VrfState](`declare_compact_vrf_state`) requesting the given callback,
+/// see [`compact`].
+///
+/// Example
+/// ```ignore
+/// #[derive(Accounts)]
+/// pub struct RequestRandomness<'info> {
+///     #[account(
+/// 		init,
+/// 		payer = payer,
+/// 		space = vrf_sdk::account_size_for(
+/// 			&accounts::OnRandomnessResponse { vrf: Pubkey::default() },
+/// 			&instruction::OnRandomnessResponse { vrf_result: Default::default() },
+/// 		),
+/// 		seeds = [
+/// 			// Specify PDA seeds
+/// 		],
+/// 		bump,
+/// 	)]
+///     vrf: AccountLoader<'info, VrfState>,
+///     system_program: Program<'info, System>,
+/// }
+/// ```
+pub fn account_size_for<CB, IX>(callback: &CB, callback_ix_data: &IX) -> usize
+where
+    CB: ToAccountMetas,
+    IX: InstructionData,
+{
+    compact::account_size(
+        callback.to_account_metas(None).len(),
+        callback_ix_data.data().len(),
+    )
+}
+
+/// Same as [`account_size_for`], for a compact [vrf_state](`vrf_state`) with program fields.
+///
+/// A full `vrf_state` with program fields use `8 + std::mem::size_of::<VrfState>()` instead.
+pub fn account_size_for_state<VRF, CB, IX>(callback: &CB, callback_ix_data: &IX) -> usize
+where
+    VRF: std::ops::Deref<Target = compact::VrfCompactAccountData>,
+    CB: ToAccountMetas,
+    IX: InstructionData,
+{
+    account_size_for(callback, callback_ix_data) + std::mem::size_of::<VRF>()
+        - std::mem::size_of::<compact::VrfCompactAccountData>()
+}
+
 /// Request a new randomness value.
 /// The supplied `VrfState` should be created seperately for each request
 ///
+/// The request is bound to the given [`Oracle`](`oracle::Oracle`),
+/// only its VRF-server will fulfill it.
+///
 /// Example
 /// ```ignore
 /// vrf_sdk::request_randomness(
 /// 	&seeds,
 /// 	&ctx.accounts.vrf,
+/// 	&ctx.accounts.oracle,
 /// 	// This struct will be auto generated by anchor
 /// 	// if you has an struct like
 /// 	//
@@ -61,58 +123,142 @@ pub const ACCOUNT_SIZE: usize = std::mem::size_of::<vrf::VrfAccountData>() + /*
 /// 	},
 /// )?;
 /// ```
-pub fn request_randomness<VRF, CB, IX>(
+pub fn request_randomness<'info, VRF, CB, IX>(
     seeds: &[u8],
-    vrf: &AccountLoader<'_, VRF>,
+    vrf: &AccountLoader<'info, VRF>,
+    oracle: &AccountInfo<'info>,
     callback: CB,
     callback_ix_data: IX,
 ) -> anchor_lang::Result<()>
 where
-    VRF: DerefMut<Target = vrf::VrfAccountData> + ZeroCopy + Owner,
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: vrf::VrfLayout,
     CB: ToAccountMetas,
     IX: InstructionData,
 {
-    if seeds.is_empty() || seeds.iter().map(|v| *v as u32).sum::<u32>() == 0 {
-        return Err(Error::AnchorError(AnchorError {
-            error_name: "Vrf seeds is zeroed or empty".to_owned(),
-            error_code_number: ErrorCode::ConstraintSeeds.into(),
-            error_msg: "Vrf seeds is zeroed or empty".to_owned(),
-            error_origin: None,
-            compared_values: None,
-        }));
+    request_randomness_with_fee(seeds, vrf, oracle, None, callback, callback_ix_data)
+}
+
+/// Same as [`request_randomness`], paying the [`Oracle::fee`](`oracle::Oracle::fee`)
+/// to the oracle fee vault.
+///
+/// The request fails if the oracle ask for a fee and `fee_payer` is `None`.
+/// See also [`VrfRequestBuilder`].
+///
+/// Example
+/// ```ignore
+/// vrf_sdk::request_randomness_with_fee(
+/// 	&seeds,
+/// 	&ctx.accounts.vrf,
+/// 	&ctx.accounts.oracle,
+/// 	Some(&vrf_sdk::oracle::FeePayer::Lamports {
+/// 		payer: &ctx.accounts.user.to_account_info(),
+/// 		fee_vault: &ctx.accounts.fee_vault,
+/// 		system_program: &ctx.accounts.system_program.to_account_info(),
+/// 	}),
+/// 	accounts::OnRandomnessResponse {
+/// 		vrf: ctx.accounts.vrf.key(),
+/// 	},
+/// 	instruction::OnRandomnessResponse {
+///     	params: params,
+/// 	},
+/// )?;
+/// ```
+pub fn request_randomness_with_fee<'info, VRF, CB, IX>(
+    seeds: &[u8],
+    vrf: &AccountLoader<'info, VRF>,
+    oracle: &AccountInfo<'info>,
+    fee_payer: Option<&oracle::FeePayer<'_, 'info>>,
+    callback: CB,
+    callback_ix_data: IX,
+) -> anchor_lang::Result<()>
+where
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: vrf::VrfLayout,
+    CB: ToAccountMetas,
+    IX: InstructionData,
+{
+    let mut builder = VrfRequestBuilder::new(vrf, oracle)
+        .seeds(seeds)
+        .callback(callback, callback_ix_data);
+    if let Some(fee_payer) = fee_payer {
+        builder = builder.fee_payer(fee_payer);
     }
+    builder.request()
+}
 
+/// Cancel a `Pending` request once `timeout` seconds have elapsed since it was requested,
+/// so the VRF-server will not fulfill it anymore.
+///
+/// The `vrf` account should be closed back to the requester in the same instruction.
+///
+/// Example
+/// ```ignore
+/// #[derive(Accounts)]
+/// pub struct CancelRequest<'info> {
+///     #[account(mut)]
+///     user: Signer<'info>,
+///     #[account(
+/// 		mut,
+/// 		close = user,
+/// 		seeds = [
+/// 			// Same PDA seeds as the request
+/// 		],
+/// 		bump,
+/// 	)]
+///     vrf: AccountLoader<'info, VrfState>,
+/// }
+///
+/// vrf_sdk::cancel_request(&ctx.accounts.vrf, 10 * 60)?;
+/// ```
+pub fn cancel_request<VRF>(vrf: &AccountLoader<'_, VRF>, timeout: i64) -> anchor_lang::Result<()>
+where
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: vrf::VrfLayout,
+{
     let vrf_pubkey = vrf.key();
-    let vrf = &mut vrf.load_init()?;
-    let vrf = vrf.deref_mut();
-
-    vrf.seeds[0..seeds.len().min(vrf::SEEDS_BYTE_LEN)]
-        .copy_from_slice(&seeds[0..seeds.len().min(vrf::SEEDS_BYTE_LEN)]);
-
-    vrf.request_timestamp = Clock::get()?.unix_timestamp;
-    vrf.callback.program_id = VRF::owner();
-
-    let metas = callback.to_account_metas(None);
-    vrf.callback.accounts_len = metas.len() as u32;
-    for (i, meta) in metas.iter().enumerate() {
-        vrf.callback.accounts[i] = vrf::AccountMetaPacked {
-            pubkey: meta.pubkey,
-            is_signer: meta.is_signer,
-            is_writable: meta.is_writable,
-        };
-    }
+    vrf.load_mut()?.deref_mut().cancel_request(timeout)?;
 
-    let ix_data = callback_ix_data.data();
-    vrf.callback.ix_data_len = ix_data.len() as u32;
-    vrf.callback.ix_data[0..ix_data.len()].copy_from_slice(&ix_data);
+    emit!(vrf::VrfRequestCancelled { vrf: vrf_pubkey });
+    Ok(())
+}
+
+/// Upgrade a `vrf` account written by an older sdk to [`LAYOUT_VERSION`](`vrf::LAYOUT_VERSION`),
+/// so in-flight requests keep working after a layout change.
+///
+/// Only the program owning `vrf` can write it, so it should expose an instruction calling this,
+/// accounts owned by the coordinator are upgraded with [`coordinator::migrate_instruction`].
+///
+/// Example
+/// ```ignore
+/// #[derive(Accounts)]
+/// pub struct MigrateVrf<'info> {
+///     #[account(mut)]
+///     vrf: AccountLoader<'info, VrfState>,
+/// }
+///
+/// vrf_sdk::migrate_account(&ctx.accounts.vrf)?;
+/// ```
+pub fn migrate_account<VRF>(vrf: &AccountLoader<'_, VRF>) -> anchor_lang::Result<()>
+where
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: vrf::VrfLayout,
+{
+    let from_version = vrf.load_mut()?.deref_mut().migrate_layout()?;
+    if from_version != vrf::LAYOUT_VERSION {
+        emit!(vrf::VrfAccountMigrated {
+            vrf: vrf.key(),
+            from_version,
+            to_version: vrf::LAYOUT_VERSION,
+        });
+    }
 
-    emit!(vrf::VrfRequestRandomness { vrf: vrf_pubkey });
     Ok(())
 }
 
 #[zero_copy]
 #[derive(AnchorSerialize, AnchorDeserialize)]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct VrfResult {
     pub result: [u8; vrf::RESULT_BYTE_LEN],
 }
@@ -125,44 +271,60 @@ impl Default for VrfResult {
     }
 }
 
-impl VrfResult {
-    /// Generate a random number from the `VrfState`
-    /// that satisfy the provided range.
-    ///
-    /// Example
-    /// ```ignore
-    ///		let result = vrf_result.random(0..=100)?;
-    /// 	assert!(0 <= result && result <= 100);
-    /// ```
-    pub fn random<Int>(self, range: RangeInclusive<Int>) -> anchor_lang::Result<Int>
-    where
-        Int: PrimInt + AsPrimitive<i128>,
-        i128: AsPrimitive<Int>,
-    {
-        // compile time assertion that `vrf::VrfAccountData::RESULT_BYTE_LEN`
-        // must contains at least 16 bytes
-        const _: [(); 0 - !(vrf::RESULT_BYTE_LEN >= 16) as usize] = [];
-
-        // ensure that the vrf has completed
-        if &self.result == &[0u8; vrf::RESULT_BYTE_LEN]
-            || &self.result == &vrf::VRF_RESULT_DISCRIMINATOR
-        {
-            return Err(Error::AnchorError(AnchorError {
-                error_name: "VrfNotFulfilled".to_owned(),
-                error_code_number: 7777,
-                error_msg: "vrf_sdk::random() called on an empty VrfState".to_owned(),
-                error_origin: None,
-                compared_values: None,
-            }));
-        }
+/// VRF proof of a [`VrfResult`], filled in by the VRF-server
+/// the same way as [`VrfResult`] when used as a callback parameter.
+///
+/// Example
+/// ```ignore
+/// pub fn on_randomness_response(
+///     ctx: Context<OnRandomnessResponse>,
+///     vrf_result: vrf_sdk::VrfResult,
+///     vrf_proof: vrf_sdk::VrfProof,
+/// ) -> anchor_lang::Result<()> {
+///     let mut vrf = ctx.accounts.vrf.load_mut()?;
+///     vrf.result = vrf_result;
+///     vrf.proof = vrf_proof.proof;
+///     vrf.verify(&ORACLE_PUBLIC_KEY)?;
+///     // ...
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct VrfProof {
+    pub proof: [u8; vrf::PROOF_BYTE_LEN],
+}
 
-        // convert the first 16 byte from the result to an i128
-        // we assert at compile time that the result contains at least 16 bytes, so unwrap is ok
-        let rand = i128::from_be_bytes(self.result[0..16].try_into().unwrap());
+impl Default for VrfProof {
+    fn default() -> Self {
+        let mut proof = [0u8; vrf::PROOF_BYTE_LEN];
+        proof[0..vrf::VRF_PROOF_DISCRIMINATOR.len()].copy_from_slice(&vrf::VRF_PROOF_DISCRIMINATOR);
+        Self { proof }
+    }
+}
 
-        // apply the required range
-        let bound: i128 = (*range.end() - *range.start()).as_();
-        let out = ((rand % bound) + range.start().as_()).as_();
-        Ok(out)
+// borsh only implements arrays of a few fixed sizes, so we (de)serialize the proof by hand
+impl AnchorSerialize for VrfProof {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.proof)
     }
 }
+
+impl AnchorDeserialize for VrfProof {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        if buf.len() < vrf::PROOF_BYTE_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Unexpected length of input",
+            ));
+        }
+
+        let mut proof = [0u8; vrf::PROOF_BYTE_LEN];
+        proof.copy_from_slice(&buf[..vrf::PROOF_BYTE_LEN]);
+        *buf = &buf[vrf::PROOF_BYTE_LEN..];
+        Ok(Self { proof })
+    }
+}
+
+pub(crate) fn invalid_proof_error(msg: &str) -> Error {
+    msg!("Vrf proof verification failed: {}", msg);
+    error!(error::VrfError::InvalidProof)
+}
@@ -0,0 +1,257 @@
+//! Requests from native (non-Anchor) programs, behind the `native` feature.
+//!
+//! The VRF account has the same layout as with [`request_randomness`](`crate::request_randomness`):
+//! a [`VrfAccountData`] if the account is at least [`ACCOUNT_SIZE`] bytes,
+//! a [compact account](`crate::compact`) otherwise. So the VRF-server fulfill both kinds identically.
+//!
+//! The callback is a raw [`Instruction`] to the program owning the VRF account, stored as is:
+//! the VRF-server write the [`VrfResult`] at `result_offset` in its data,
+//! and the [`VrfProof`](`crate::VrfProof`) at `proof_offset` if any, see [`CallbackOffsets`].
+//!
+//! Example
+//! ```ignore
+//! let callback = Instruction {
+//!     program_id: *program_id,
+//!     accounts: vec![AccountMeta::new(*vrf.key, false), AccountMeta::new_readonly(*oracle.key, false)],
+//!     // tag of the callback instruction, followed by the result and the proof
+//!     data: [&[1u8], &[0u8; 32][..], &[0u8; 81][..]].concat(),
+//! };
+//! vrf_sdk::native::request_randomness(&seeds, vrf, oracle, None, &callback, 1, Some(33))?;
+//!
+//! // in the callback instruction
+//! let result = vrf_sdk::native::fulfill(program_id, vrf, oracle, &data[1..33].try_into().unwrap(), &data[33..].try_into().unwrap())?;
+//! ```
+
+use anchor_lang::{
+    __private::bytemuck,
+    prelude::*,
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction},
+    Discriminator,
+};
+
+use crate::{
+    compact::VrfCompactAccountData,
+    oracle::{FeePayer, Oracle},
+    vrf::{
+        validate_request, CallbackOffsets, VrfAccountData, VrfLayout, VrfRequestCancelled,
+        VrfRequestRandomness, PROOF_BYTE_LEN, RESULT_BYTE_LEN,
+    },
+    VrfResult, ACCOUNT_SIZE,
+};
+
+/// Request a new randomness value from a native program.
+///
+/// `vrf` is a new account owned by `callback.program_id`, its discriminator is written here.
+/// An initialized `vrf` account is re-armed for a new round instead, once its previous request is closed.
+/// `fee_payer` pay the [`Oracle::fee`], if any.
+pub fn request_randomness<'info>(
+    seeds: &[u8],
+    vrf: &AccountInfo<'info>,
+    oracle: &AccountInfo<'info>,
+    fee_payer: Option<&FeePayer<'_, 'info>>,
+    callback: &Instruction,
+    result_offset: usize,
+    proof_offset: Option<usize>,
+) -> ProgramResult {
+    process_request(
+        seeds,
+        vrf,
+        oracle,
+        fee_payer,
+        callback,
+        result_offset,
+        proof_offset,
+    )
+    .map_err(program_error)
+}
+
+/// Cancel a `Pending` request once `timeout` seconds have elapsed since it was requested,
+/// see [`cancel_request`](`crate::cancel_request`).
+pub fn cancel_request(program_id: &Pubkey, vrf: &AccountInfo<'_>, timeout: i64) -> ProgramResult {
+    with_vrf_mut(program_id, vrf, |vrf| vrf.cancel_request(timeout)).map_err(program_error)?;
+
+    emit!(VrfRequestCancelled { vrf: vrf.key() });
+    Ok(())
+}
+
+/// Store the `result` and `proof` supplied to the callback, verify them against the
+/// [`Oracle`] the request is bound to and mark the request `Fulfilled`.
+pub fn fulfill(
+    program_id: &Pubkey,
+    vrf: &AccountInfo<'_>,
+    oracle: &AccountInfo<'_>,
+    result: &[u8; RESULT_BYTE_LEN],
+    proof: &[u8; PROOF_BYTE_LEN],
+) -> std::result::Result<VrfResult, ProgramError> {
+    with_vrf_mut(program_id, vrf, |vrf| vrf.fulfill(oracle, result, proof)).map_err(program_error)
+}
+
+fn process_request<'info>(
+    seeds: &[u8],
+    vrf: &AccountInfo<'info>,
+    oracle: &AccountInfo<'info>,
+    fee_payer: Option<&FeePayer<'_, 'info>>,
+    callback: &Instruction,
+    result_offset: usize,
+    proof_offset: Option<usize>,
+) -> anchor_lang::Result<()> {
+    let offsets = CallbackOffsets {
+        result: result_offset,
+        proof: proof_offset,
+    };
+    validate_request(seeds, &callback.accounts, &callback.data)?;
+    offsets.validate(callback.data.len())?;
+    check_owner(&callback.program_id, vrf)?;
+
+    let oracle_data = Oracle::load(oracle)?;
+    oracle_data.require_active()?;
+    let fee_paid = oracle_data.pay_fee(fee_payer)?;
+
+    let round = {
+        let mut data = vrf.try_borrow_mut_data()?;
+        // same checks as `AccountLoader::load_init`, or `load_mut` for a new round
+        if data.len() < 8 {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        let rearm = data[0..8] != [0u8; 8];
+        if rearm && data[0..8] != VrfAccountData::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+
+        let round = match VrfAccountMut::from_data(&mut data)? {
+            VrfAccountMut::Full(vrf) => {
+                if rearm {
+                    vrf.rearm_request()?;
+                }
+                vrf.init_request(
+                    &mut [],
+                    0,
+                    seeds,
+                    oracle.key(),
+                    callback.program_id,
+                    &callback.accounts,
+                    &callback.data,
+                    Some(offsets),
+                    None,
+                    fee_paid,
+                )?;
+                vrf.round
+            }
+            VrfAccountMut::Compact(vrf, callback_data) => {
+                if rearm {
+                    vrf.rearm_request()?;
+                }
+                vrf.init_request(
+                    callback_data,
+                    0,
+                    seeds,
+                    oracle.key(),
+                    callback.program_id,
+                    &callback.accounts,
+                    &callback.data,
+                    Some(offsets),
+                    None,
+                    fee_paid,
+                )?;
+                vrf.round
+            }
+        };
+        data[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+        round
+    };
+
+    emit!(VrfRequestRandomness {
+        vrf: vrf.key(),
+        round,
+    });
+    Ok(())
+}
+
+/// A VRF account of either layout, borrowed mutably.
+enum VrfAccountMut<'a> {
+    Full(&'a mut VrfAccountData),
+    Compact(&'a mut VrfCompactAccountData, &'a mut [u8]),
+}
+
+impl<'a> VrfAccountMut<'a> {
+    /// Split the whole account `data`, discriminator included, see [`VrfAccountView`](`crate::vrf::VrfAccountView`).
+    fn from_data(data: &'a mut [u8]) -> anchor_lang::Result<Self> {
+        if data.len() >= ACCOUNT_SIZE {
+            return Ok(Self::Full(bytemuck::from_bytes_mut(
+                &mut data[8..ACCOUNT_SIZE],
+            )));
+        }
+
+        let (vrf, callback_data) = VrfCompactAccountData::split_mut(&mut data[8..])?;
+        Ok(Self::Compact(vrf, callback_data))
+    }
+
+    fn cancel_request(self, timeout: i64) -> anchor_lang::Result<()> {
+        match self {
+            Self::Full(vrf) => vrf.cancel_request(timeout),
+            Self::Compact(vrf, _) => vrf.cancel_request(timeout),
+        }
+    }
+
+    fn fulfill(
+        self,
+        oracle: &AccountInfo<'_>,
+        result: &[u8; RESULT_BYTE_LEN],
+        proof: &[u8; PROOF_BYTE_LEN],
+    ) -> anchor_lang::Result<VrfResult> {
+        match self {
+            Self::Full(vrf) => {
+                vrf.result = VrfResult { result: *result };
+                vrf.proof = *proof;
+                let result = vrf.verify_oracle(oracle)?;
+                vrf.set_fulfilled()?;
+                Ok(result)
+            }
+            Self::Compact(vrf, _) => {
+                vrf.result = VrfResult { result: *result };
+                vrf.proof = *proof;
+                let result = vrf.verify_oracle(oracle)?;
+                vrf.set_fulfilled()?;
+                Ok(result)
+            }
+        }
+    }
+}
+
+/// Borrow an initialized VRF account owned by `program_id`.
+fn with_vrf_mut<T>(
+    program_id: &Pubkey,
+    vrf: &AccountInfo<'_>,
+    f: impl FnOnce(VrfAccountMut<'_>) -> anchor_lang::Result<T>,
+) -> anchor_lang::Result<T> {
+    check_owner(program_id, vrf)?;
+
+    let mut data = vrf.try_borrow_mut_data()?;
+    if data.len() < 8 {
+        return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+    }
+    if data[0..8] != VrfAccountData::DISCRIMINATOR {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+
+    f(VrfAccountMut::from_data(&mut data)?)
+}
+
+fn check_owner(program_id: &Pubkey, vrf: &AccountInfo<'_>) -> anchor_lang::Result<()> {
+    if vrf.owner != program_id {
+        return Err(error!(ErrorCode::AccountOwnedByWrongProgram)
+            .with_account_name("vrf")
+            .with_pubkeys((*vrf.owner, *program_id)));
+    }
+    if !vrf.is_writable {
+        return Err(error!(ErrorCode::AccountNotMutable).with_account_name("vrf"));
+    }
+
+    Ok(())
+}
+
+/// Log the anchor error, as the anchor entrypoint does, before handing it to the runtime.
+fn program_error(err: Error) -> ProgramError {
+    err.log();
+    err.into()
+}
@@ -0,0 +1,312 @@
+//! Oracle registry, published by the `vrf-coordinator` program.
+//!
+//! Each VRF-server register an [`Oracle`] account, derived from its fee payer key,
+//! which hold the VRF public key used to verify its proofs.
+//! A randomness request is bound to one oracle, see [`VrfAccountData::oracle`](`crate::vrf::VrfAccountData::oracle`).
+//!
+//! An oracle can ask a fee for each request, paid in lamports or in SPL token
+//! to its `fee_vault`, see [`request_randomness_with_fee`](`crate::request_randomness_with_fee`).
+
+use std::io::Write;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke,
+        system_instruction,
+    },
+    Discriminator,
+};
+
+use crate::{coordinator, ecvrf::VrfPublicKey, error::VrfError};
+
+pub const ORACLE_SEED: &[u8] = b"oracle";
+
+/// The SPL token program, used when the fee is paid in SPL token.
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+/// The [`Oracle`] account address of the given fee payer `authority`.
+pub fn address(authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ORACLE_SEED, authority.as_ref()], &coordinator::ID).0
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleStatus {
+    /// The oracle accept new requests.
+    Active,
+    /// The oracle does not accept new requests.
+    Disabled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Oracle {
+    /// The fee payer of the VRF-server, the only one allowed to fulfill the requests.
+    pub authority: Pubkey,
+    /// The VRF public key of the VRF-server.
+    pub vrf_public_key: VrfPublicKey,
+    pub status: OracleStatus,
+    /// Fee of each request, in lamports or in the smallest unit of `fee_mint`.
+    pub fee: u64,
+    /// The SPL token the fee is paid in, `Pubkey::default()` for lamports.
+    pub fee_mint: Pubkey,
+    /// Who receive the fees, a system account for lamports,
+    /// a token account of `fee_mint` for SPL token.
+    pub fee_vault: Pubkey,
+}
+
+impl Oracle {
+    /// Account size, including the discriminator.
+    pub const SIZE: usize = 8 + 32 + crate::ecvrf::PUBLIC_KEY_BYTE_LEN + 1 + 8 + 32 + 32;
+
+    /// Load an oracle from the registry, checking owner and discriminator.
+    pub fn load(account: &AccountInfo<'_>) -> anchor_lang::Result<Self> {
+        if account.owner != &coordinator::ID {
+            return Err(error!(ErrorCode::AccountOwnedByWrongProgram)
+                .with_account_name("oracle")
+                .with_pubkeys((*account.owner, coordinator::ID)));
+        }
+
+        let data = account.try_borrow_data()?;
+        Self::try_deserialize(&mut &data[..])
+    }
+
+    /// Ensure the oracle accept new requests.
+    pub fn require_active(&self) -> anchor_lang::Result<()> {
+        if self.status != OracleStatus::Active {
+            return Err(error!(VrfError::OracleDisabled).with_account_name("oracle"));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the fee is paid in lamports rather than SPL token.
+    pub fn is_fee_in_lamports(&self) -> bool {
+        self.fee_mint == Pubkey::default()
+    }
+
+    /// Transfer the oracle fee from `payer` to the fee vault.
+    ///
+    /// Return the amount paid, `0` if the oracle does not ask for a fee.
+    pub fn pay_fee(&self, payer: Option<&FeePayer<'_, '_>>) -> anchor_lang::Result<u64> {
+        if self.fee == 0 {
+            return Ok(0);
+        }
+
+        let payer = match payer {
+            Some(payer) => payer,
+            None => return Err(fee_not_paid_error("the oracle ask for a fee")),
+        };
+
+        let fee_vault = payer.fee_vault();
+        if fee_vault.key() != self.fee_vault {
+            return Err(error!(ErrorCode::ConstraintAddress)
+                .with_account_name("fee_vault")
+                .with_pubkeys((fee_vault.key(), self.fee_vault)));
+        }
+
+        match payer {
+            FeePayer::Lamports {
+                payer,
+                fee_vault,
+                system_program,
+            } => {
+                if !self.is_fee_in_lamports() {
+                    return Err(fee_not_paid_error("the fee must be paid in SPL token"));
+                }
+
+                invoke(
+                    &system_instruction::transfer(&payer.key(), &fee_vault.key(), self.fee),
+                    &[
+                        (*payer).clone(),
+                        (*fee_vault).clone(),
+                        (*system_program).clone(),
+                    ],
+                )?;
+            }
+            FeePayer::Token {
+                source,
+                fee_vault,
+                authority,
+                token_program,
+            } => {
+                if self.is_fee_in_lamports() {
+                    return Err(fee_not_paid_error("the fee must be paid in lamports"));
+                }
+                if token_program.key() != TOKEN_PROGRAM_ID {
+                    return Err(error!(ErrorCode::InvalidProgramId)
+                        .with_account_name("token_program")
+                        .with_pubkeys((token_program.key(), TOKEN_PROGRAM_ID)));
+                }
+
+                // spl_token::instruction::TokenInstruction::Transfer,
+                // the token program check that both accounts use the vault mint
+                let mut data = vec![3u8];
+                data.extend_from_slice(&self.fee.to_le_bytes());
+                let ix = Instruction {
+                    program_id: TOKEN_PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new(source.key(), false),
+                        AccountMeta::new(fee_vault.key(), false),
+                        AccountMeta::new_readonly(authority.key(), true),
+                    ],
+                    data,
+                };
+
+                invoke(
+                    &ix,
+                    &[
+                        (*source).clone(),
+                        (*fee_vault).clone(),
+                        (*authority).clone(),
+                        (*token_program).clone(),
+                    ],
+                )?;
+            }
+        }
+
+        Ok(self.fee)
+    }
+}
+
+/// The accounts paying the oracle fee of a request.
+pub enum FeePayer<'a, 'info> {
+    /// Pay in lamports, `payer` must be a signer.
+    Lamports {
+        payer: &'a AccountInfo<'info>,
+        fee_vault: &'a AccountInfo<'info>,
+        system_program: &'a AccountInfo<'info>,
+    },
+    /// Pay in SPL token from the `source` token account, `authority` must be a signer.
+    Token {
+        source: &'a AccountInfo<'info>,
+        fee_vault: &'a AccountInfo<'info>,
+        authority: &'a AccountInfo<'info>,
+        token_program: &'a AccountInfo<'info>,
+    },
+}
+
+impl<'a, 'info> FeePayer<'a, 'info> {
+    /// Parse the accounts of [`to_account_infos`](`Self::to_account_infos`).
+    pub fn from_accounts(accounts: &'a [AccountInfo<'info>]) -> Option<Self> {
+        match accounts {
+            [payer, fee_vault, system_program] => Some(Self::Lamports {
+                payer,
+                fee_vault,
+                system_program,
+            }),
+            [source, fee_vault, authority, token_program] => Some(Self::Token {
+                source,
+                fee_vault,
+                authority,
+                token_program,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn fee_vault(&self) -> &'a AccountInfo<'info> {
+        match self {
+            Self::Lamports { fee_vault, .. } | Self::Token { fee_vault, .. } => fee_vault,
+        }
+    }
+
+    pub fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        match self {
+            Self::Lamports {
+                payer,
+                fee_vault,
+                system_program,
+            } => vec![
+                (*payer).clone(),
+                (*fee_vault).clone(),
+                (*system_program).clone(),
+            ],
+            Self::Token {
+                source,
+                fee_vault,
+                authority,
+                token_program,
+            } => vec![
+                (*source).clone(),
+                (*fee_vault).clone(),
+                (*authority).clone(),
+                (*token_program).clone(),
+            ],
+        }
+    }
+
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        match self {
+            Self::Lamports {
+                payer,
+                fee_vault,
+                system_program,
+            } => vec![
+                AccountMeta::new(payer.key(), true),
+                AccountMeta::new(fee_vault.key(), false),
+                AccountMeta::new_readonly(system_program.key(), false),
+            ],
+            Self::Token {
+                source,
+                fee_vault,
+                authority,
+                token_program,
+            } => vec![
+                AccountMeta::new(source.key(), false),
+                AccountMeta::new(fee_vault.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+                AccountMeta::new_readonly(token_program.key(), false),
+            ],
+        }
+    }
+}
+
+fn fee_not_paid_error(msg: &str) -> Error {
+    msg!("The vrf oracle fee is not paid: {}", msg);
+    error!(VrfError::FeeNotPaid)
+}
+
+impl Discriminator for Oracle {
+    const DISCRIMINATOR: [u8; 8] = [139, 194, 131, 179, 140, 179, 229, 244];
+}
+
+impl Owner for Oracle {
+    fn owner() -> Pubkey {
+        coordinator::ID
+    }
+}
+
+impl AccountSerialize for Oracle {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+        if writer.write_all(&Self::DISCRIMINATOR).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+
+        if AnchorSerialize::serialize(self, writer).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for Oracle {
+    fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        if buf.len() < Self::DISCRIMINATOR.len() {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        let given_disc = &buf[..8];
+        if Self::DISCRIMINATOR != given_disc {
+            return Err(error!(ErrorCode::AccountDiscriminatorMismatch).with_account_name("Oracle"));
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let mut data: &[u8] = &buf[8..];
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
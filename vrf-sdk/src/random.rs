@@ -0,0 +1,342 @@
+//! Sampling random values from a [`VrfResult`].
+//!
+//! All values are sampled without modulo bias: a candidate falling in the
+//! incomplete last "bucket" is rejected and a new one is derived by re-hashing
+//! the 32 bytes of the result, so every value of the range has the same probability.
+//!
+//! Compute cost: each sha256 yields 2 candidates, and more than half of the candidates
+//! are always accepted. [`VrfStream::shuffle`], [`VrfStream::choose_weighted`] and
+//! [`VrfStream::sample_without_replacement`] draw all their candidates from a single
+//! stream index, so their cost grows linearly with the number of items. They fail with
+//! [`VrfError::TooManyItems`] beyond [`MAX_SAMPLE_LEN`] items, so each call stays within
+//! the compute budget and the heap of a transaction.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use num_traits::{AsPrimitive, PrimInt};
+use solana_program::hash::hashv;
+
+use crate::{error::VrfError, vrf, VrfResult};
+
+/// Most items [`VrfStream::shuffle`] shuffles and [`VrfStream::sample_without_replacement`] picks at once.
+pub const MAX_SAMPLE_LEN: usize = 256;
+
+// compile time assertion that a result is exactly 2 `u128` candidates
+const _: [(); 2 * 16] = [(); vrf::RESULT_BYTE_LEN];
+
+/// Deterministic source of uniform `u128` candidates derived from a [`VrfResult`].
+///
+/// Yield the two halves of the result, then the two halves of `sha256(result)`,
+/// `sha256(sha256(result))` and so on.
+struct Candidates {
+    block: [u8; vrf::RESULT_BYTE_LEN],
+    index: usize,
+}
+
+impl Candidates {
+    fn new(result: &[u8; vrf::RESULT_BYTE_LEN]) -> Self {
+        Self {
+            block: *result,
+            index: 0,
+        }
+    }
+
+    /// Candidates of the `index`-th value of a [`VrfStream`], starting from `sha256(result || index)`.
+    fn nth(result: &[u8; vrf::RESULT_BYTE_LEN], index: u64) -> Self {
+        Self::new(&hashv(&[result, &index.to_le_bytes()]).to_bytes())
+    }
+
+    fn next(&mut self) -> u128 {
+        if self.index == 2 {
+            self.block = hashv(&[&self.block]).to_bytes();
+            self.index = 0;
+        }
+
+        let offset = self.index * 16;
+        self.index += 1;
+        // a block is exactly 2 candidates, so unwrap is ok
+        u128::from_be_bytes(self.block[offset..offset + 16].try_into().unwrap())
+    }
+
+    /// A value uniformly distributed in `0..=span`.
+    fn uniform(&mut self, span: u128) -> u128 {
+        if span == u128::MAX {
+            return self.next();
+        }
+
+        let count = span + 1;
+        // 2^128 % count, the size of the incomplete last bucket
+        let rest = (u128::MAX % count + 1) % count;
+        loop {
+            let candidate = self.next();
+            // always accept more than half of the candidates, so this terminates quickly
+            if candidate <= u128::MAX - rest {
+                return candidate % count;
+            }
+        }
+    }
+}
+
+impl VrfResult {
+    /// Ensure the oracle has supplied the result.
+    fn fulfilled_result(&self) -> std::result::Result<&[u8; vrf::RESULT_BYTE_LEN], VrfError> {
+        if self.result == [0u8; vrf::RESULT_BYTE_LEN]
+            || self.result == vrf::VRF_RESULT_DISCRIMINATOR
+        {
+            return Err(VrfError::NotFulfilled);
+        }
+
+        Ok(&self.result)
+    }
+
+    /// Generate a random number from the `VrfState`
+    /// that satisfy the provided range, both ends included.
+    ///
+    /// Every value of the range has the same probability.
+    ///
+    /// Example
+    /// ```ignore
+    ///		let result = vrf_result.random(0..=100)?;
+    /// 	assert!(0 <= result && result <= 100);
+    /// ```
+    pub fn random<Int>(self, range: RangeInclusive<Int>) -> std::result::Result<Int, VrfError>
+    where
+        Int: PrimInt + AsPrimitive<i128>,
+        i128: AsPrimitive<Int>,
+    {
+        let result = self.fulfilled_result()?;
+        sample_range(&mut Candidates::new(result), range)
+    }
+
+    /// Generate the `index`-th random number derived from the `VrfState`,
+    /// independent from the ones at any other index.
+    ///
+    /// Each call cost a single sha256, plus one per (rare) rejected candidate.
+    ///
+    /// Example
+    /// ```ignore
+    /// 	let first_dice = vrf_result.nth_random(0, 1..=6)?;
+    /// 	let second_dice = vrf_result.nth_random(1, 1..=6)?;
+    /// ```
+    pub fn nth_random<Int>(
+        self,
+        index: u64,
+        range: RangeInclusive<Int>,
+    ) -> std::result::Result<Int, VrfError>
+    where
+        Int: PrimInt + AsPrimitive<i128>,
+        i128: AsPrimitive<Int>,
+    {
+        let result = self.fulfilled_result()?;
+        sample_range(&mut Candidates::nth(result, index), range)
+    }
+
+    /// A [`VrfStream`] yielding an arbitrary number of independent random numbers,
+    /// the same as calling [`nth_random`](`Self::nth_random`) with index `0`, `1`, `2`...
+    ///
+    /// Example
+    /// ```ignore
+    /// 	let mut stream = vrf_result.stream()?;
+    /// 	let dices = (0..5)
+    /// 		.map(|_| stream.next_random(1..=6))
+    /// 		.collect::<Result<Vec<u8>, _>>()?;
+    /// ```
+    pub fn stream(self) -> std::result::Result<VrfStream, VrfError> {
+        let result = self.fulfilled_result()?;
+        Ok(VrfStream {
+            result: *result,
+            index: 0,
+        })
+    }
+
+    /// Shuffle `items` in place, see [`VrfStream::shuffle`].
+    pub fn shuffle<T>(self, items: &mut [T]) -> std::result::Result<(), VrfError> {
+        self.stream()?.shuffle(items)
+    }
+
+    /// Pick an index of `weights`, see [`VrfStream::choose_weighted`].
+    pub fn choose_weighted(self, weights: &[u64]) -> std::result::Result<usize, VrfError> {
+        self.stream()?.choose_weighted(weights)
+    }
+
+    /// Pick `k` distinct values in `0..n`, see [`VrfStream::sample_without_replacement`].
+    pub fn sample_without_replacement(
+        self,
+        n: u64,
+        k: usize,
+    ) -> std::result::Result<Vec<u64>, VrfError> {
+        self.stream()?.sample_without_replacement(n, k)
+    }
+
+    /// `true` with a probability of `p_bps / 10_000`, see [`VrfStream::bernoulli`].
+    pub fn bernoulli(self, p_bps: u16) -> std::result::Result<bool, VrfError> {
+        self.stream()?.bernoulli(p_bps)
+    }
+}
+
+/// Deterministic sequence of random numbers derived from a fulfilled [`VrfResult`],
+/// see [`VrfResult::stream`].
+#[derive(Debug, Clone)]
+pub struct VrfStream {
+    result: [u8; vrf::RESULT_BYTE_LEN],
+    index: u64,
+}
+
+impl VrfStream {
+    /// Index of the next random number.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Generate the next random number that satisfy the provided range, both ends included.
+    pub fn next_random<Int>(
+        &mut self,
+        range: RangeInclusive<Int>,
+    ) -> std::result::Result<Int, VrfError>
+    where
+        Int: PrimInt + AsPrimitive<i128>,
+        i128: AsPrimitive<Int>,
+    {
+        let value = sample_range(&mut self.candidates(), range)?;
+        self.index = self.index.wrapping_add(1);
+        Ok(value)
+    }
+
+    /// Shuffle `items` in place (Fisher-Yates), every permutation has the same probability.
+    ///
+    /// Costs `items.len() / 2` sha256, and takes at most [`MAX_SAMPLE_LEN`] items.
+    ///
+    /// Example
+    /// ```ignore
+    /// 	let mut cards = (0..52).collect::<Vec<u8>>();
+    /// 	stream.shuffle(&mut cards)?;
+    /// ```
+    pub fn shuffle<T>(&mut self, items: &mut [T]) -> std::result::Result<(), VrfError> {
+        if items.len() > MAX_SAMPLE_LEN {
+            return Err(VrfError::TooManyItems);
+        }
+
+        let mut candidates = self.candidates();
+        for i in (1..items.len()).rev() {
+            let j = candidates.uniform(i as u128) as usize;
+            items.swap(i, j);
+        }
+
+        self.index = self.index.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Pick an index of `weights`, with a probability of `weights[i] / sum(weights)`.
+    ///
+    /// Items with a zero weight are never picked. Costs a single sha256.
+    ///
+    /// Example
+    /// ```ignore
+    /// 	// common: 70%, rare: 25%, legendary: 5%
+    /// 	let loot = stream.choose_weighted(&[70, 25, 5])?;
+    /// ```
+    pub fn choose_weighted(&mut self, weights: &[u64]) -> std::result::Result<usize, VrfError> {
+        // cannot overflow as long as there is less than 2^64 weights
+        let total = weights.iter().map(|weight| *weight as u128).sum::<u128>();
+        if total == 0 {
+            return Err(VrfError::InvalidWeights);
+        }
+
+        let mut target = self.candidates().uniform(total - 1);
+        self.index = self.index.wrapping_add(1);
+
+        for (i, weight) in weights.iter().enumerate() {
+            let weight = *weight as u128;
+            if target < weight {
+                return Ok(i);
+            }
+            target -= weight;
+        }
+
+        unreachable!("target is lower than the sum of weights")
+    }
+
+    /// Pick `k` distinct values in `0..n`, every ordered selection has the same probability.
+    ///
+    /// The first value can be used as the first prize, the second as the second prize, etc.
+    ///
+    /// Costs `k / 2` sha256, independently of `n`, and picks at most [`MAX_SAMPLE_LEN`] values.
+    ///
+    /// Example
+    /// ```ignore
+    /// 	// 3 winners among all the tickets
+    /// 	let winners = stream.sample_without_replacement(tickets_count, 3)?;
+    /// ```
+    pub fn sample_without_replacement(
+        &mut self,
+        n: u64,
+        k: usize,
+    ) -> std::result::Result<Vec<u64>, VrfError> {
+        if k as u128 > n as u128 {
+            return Err(VrfError::SampleTooLarge);
+        }
+        if k > MAX_SAMPLE_LEN {
+            return Err(VrfError::TooManyItems);
+        }
+
+        // partial Fisher-Yates over the virtual array `0..n`,
+        // only keeping track of the swapped positions
+        let mut swapped = BTreeMap::<u64, u64>::new();
+        let mut candidates = self.candidates();
+        let mut sample = Vec::with_capacity(k);
+        for i in 0..k as u64 {
+            let j = i + candidates.uniform((n - 1 - i) as u128) as u64;
+
+            let value_j = *swapped.get(&j).unwrap_or(&j);
+            let value_i = *swapped.get(&i).unwrap_or(&i);
+            swapped.insert(j, value_i);
+            sample.push(value_j);
+        }
+
+        self.index = self.index.wrapping_add(1);
+        Ok(sample)
+    }
+
+    /// `true` with a probability of `p_bps / 10_000`. Costs a single sha256.
+    ///
+    /// Example
+    /// ```ignore
+    /// 	// 12.5% chance of critical hit
+    /// 	let critical = stream.bernoulli(1_250)?;
+    /// ```
+    pub fn bernoulli(&mut self, p_bps: u16) -> std::result::Result<bool, VrfError> {
+        if p_bps > 10_000 {
+            return Err(VrfError::InvalidProbability);
+        }
+
+        let value = self.candidates().uniform(10_000 - 1);
+        self.index = self.index.wrapping_add(1);
+        Ok(value < p_bps as u128)
+    }
+
+    fn candidates(&self) -> Candidates {
+        Candidates::nth(&self.result, self.index)
+    }
+}
+
+fn sample_range<Int>(
+    candidates: &mut Candidates,
+    range: RangeInclusive<Int>,
+) -> std::result::Result<Int, VrfError>
+where
+    Int: PrimInt + AsPrimitive<i128>,
+    i128: AsPrimitive<Int>,
+{
+    let (start, end) = range.into_inner();
+    if start > end {
+        return Err(VrfError::InvalidRange);
+    }
+
+    // Work on the two's complement representation, so the distance between
+    // `start` and `end` is correct for every signed and unsigned `Int`.
+    let start_bits = start.as_() as u128;
+    let span = (end.as_() as u128).wrapping_sub(start_bits);
+
+    let offset = candidates.uniform(span);
+    Ok((start_bits.wrapping_add(offset) as i128).as_())
+}
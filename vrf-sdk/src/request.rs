@@ -0,0 +1,203 @@
+//! Builder of a randomness request.
+//!
+//! The request is validated before the oracle fee is paid, so an oversized callback
+//! fails with a dedicated error instead of panicking on-chain,
+//! see [`validate_request`](`crate::vrf::validate_request`).
+//!
+//! Example
+//! ```ignore
+//! vrf_sdk::VrfRequestBuilder::new(&ctx.accounts.vrf, &ctx.accounts.oracle)
+//! 	.seeds(&seeds)
+//! 	.fee_payer(&vrf_sdk::oracle::FeePayer::Lamports {
+//! 		payer: &ctx.accounts.user.to_account_info(),
+//! 		fee_vault: &ctx.accounts.fee_vault,
+//! 		system_program: &ctx.accounts.system_program.to_account_info(),
+//! 	})
+//! 	.callback(
+//! 		accounts::OnRandomnessResponse {
+//! 			vrf: ctx.accounts.vrf.key(),
+//! 		},
+//! 		instruction::OnRandomnessResponse {
+//! 			vrf_result: Default::default(),
+//! 		},
+//! 	)
+//! 	.request()?;
+//! ```
+//!
+//! The callback may target another program, see [`VrfRequestBuilder::callback_program`]
+//! ```ignore
+//! vrf_sdk::VrfRequestBuilder::new(&ctx.accounts.vrf, &ctx.accounts.oracle)
+//! 	.seeds(&seeds)
+//! 	.callback_program(game_engine::ID)
+//! 	.callback(
+//! 		game_engine::accounts::Settle {
+//! 			vrf: ctx.accounts.vrf.key(),
+//! 		},
+//! 		game_engine::instruction::Settle {
+//! 			vrf_result: Default::default(),
+//! 		},
+//! 	)
+//! 	.request()?;
+//! ```
+
+use std::{mem::size_of, ops::DerefMut};
+
+use anchor_lang::{
+    __private::bytemuck, prelude::*, solana_program::instruction::AccountMeta, InstructionData,
+    ToAccountMetas, ZeroCopy,
+};
+
+use crate::{
+    oracle::{FeePayer, Oracle},
+    vrf::{self, CallbackLookup, VrfLayout, VrfRequestRandomness},
+};
+
+pub struct VrfRequestBuilder<'a, 'info, VRF>
+where
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: VrfLayout,
+{
+    vrf: &'a AccountLoader<'info, VRF>,
+    oracle: &'a AccountInfo<'info>,
+    seeds: &'a [u8],
+    fee_payer: Option<&'a FeePayer<'a, 'info>>,
+    callback_program: Option<Pubkey>,
+    callback_accounts: Vec<AccountMeta>,
+    callback_ix_data: Vec<u8>,
+    lookup: Option<CallbackLookup>,
+    batch_len: u8,
+}
+
+impl<'a, 'info, VRF> VrfRequestBuilder<'a, 'info, VRF>
+where
+    VRF: DerefMut + ZeroCopy + Owner,
+    VRF::Target: VrfLayout,
+{
+    /// A request bound to the given [`Oracle`], without seeds nor callback.
+    pub fn new(vrf: &'a AccountLoader<'info, VRF>, oracle: &'a AccountInfo<'info>) -> Self {
+        Self {
+            vrf,
+            oracle,
+            seeds: &[],
+            fee_payer: None,
+            callback_program: None,
+            callback_accounts: Vec::new(),
+            callback_ix_data: Vec::new(),
+            lookup: None,
+            batch_len: 0,
+        }
+    }
+
+    /// At most [`SEEDS_BYTE_LEN`](`vrf::SEEDS_BYTE_LEN`) bytes, not all zero.
+    pub fn seeds(mut self, seeds: &'a [u8]) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    /// Pay the [`Oracle::fee`] to the oracle fee vault.
+    pub fn fee_payer(mut self, fee_payer: &'a FeePayer<'a, 'info>) -> Self {
+        self.fee_payer = Some(fee_payer);
+        self
+    }
+
+    /// The instruction invoked by the VRF-server, on the program owning `vrf`
+    /// unless another [`callback_program`](`Self::callback_program`) is set.
+    pub fn callback<CB, IX>(mut self, callback: CB, callback_ix_data: IX) -> Self
+    where
+        CB: ToAccountMetas,
+        IX: InstructionData,
+    {
+        self.callback_accounts = callback.to_account_metas(None);
+        self.callback_ix_data = callback_ix_data.data();
+        self
+    }
+
+    /// Invoke the callback on `program_id` instead of the program owning `vrf`,
+    /// e.g. a game engine shared by several programs.
+    ///
+    /// The VRF-server only invoke programs in its allowlist, besides the requester.
+    pub fn callback_program(mut self, program_id: Pubkey) -> Self {
+        self.callback_program = Some(program_id);
+        self
+    }
+
+    /// Append the accounts of an address lookup table to the callback accounts,
+    /// for callbacks needing more than [`MAX_CALLBACK_ACCOUNTS`](`vrf::MAX_CALLBACK_ACCOUNTS`) accounts.
+    pub fn lookup_table(mut self, lookup: CallbackLookup) -> Self {
+        self.lookup = Some(lookup);
+        self
+    }
+
+    /// Request `len` randomness values at once, fulfilled one slot per transaction,
+    /// see [`VrfAccountData::set_batch`](`vrf::VrfAccountData::set_batch`).
+    ///
+    /// Only a full VRF account, of [`ACCOUNT_SIZE`](`crate::ACCOUNT_SIZE`) bytes, can hold a batch.
+    pub fn batch(mut self, len: u8) -> Self {
+        self.batch_len = len;
+        self
+    }
+
+    /// Check the request limits without touching any account.
+    pub fn validate(&self) -> anchor_lang::Result<()> {
+        vrf::validate_request(self.seeds, &self.callback_accounts, &self.callback_ix_data)?;
+        if let Some(lookup) = &self.lookup {
+            vrf::validate_callback_lookup(lookup)?;
+        }
+        if self.batch_len != 0 {
+            vrf::validate_batch_len(self.batch_len)?;
+        }
+
+        Ok(())
+    }
+
+    /// Validate the request, pay the oracle fee and initialize the `vrf` account.
+    ///
+    /// An initialized `vrf` account is re-armed for a new round instead,
+    /// once its previous request is closed, see [`VrfAccountData::round`](`vrf::VrfAccountData::round`).
+    pub fn request(self) -> anchor_lang::Result<()> {
+        self.validate()?;
+
+        let oracle_data = Oracle::load(self.oracle)?;
+        oracle_data.require_active()?;
+        let fee_paid = oracle_data.pay_fee(self.fee_payer)?;
+
+        // same checks as `load_init`, or `load_mut` for a new round,
+        // the callback of a compact account follows the state
+        let vrf_info = self.vrf.to_account_info();
+        let rearm = vrf_info.try_borrow_data()?.get(0..8) != Some(&[0u8; 8]);
+        if rearm {
+            drop(self.vrf.load_mut()?);
+        } else {
+            drop(self.vrf.load_init()?);
+        }
+        let mut data = vrf_info.try_borrow_mut_data()?;
+        let (state, callback_data) = data[8..].split_at_mut(size_of::<VRF>());
+        let state = bytemuck::from_bytes_mut::<VRF>(state).deref_mut();
+        if rearm {
+            state.rearm_request()?;
+        }
+        state.init_request(
+            callback_data,
+            size_of::<VRF>() - size_of::<VRF::Target>(),
+            self.seeds,
+            self.oracle.key(),
+            self.callback_program.unwrap_or_else(VRF::owner),
+            &self.callback_accounts,
+            &self.callback_ix_data,
+            None,
+            self.lookup.as_ref(),
+            fee_paid,
+        )?;
+        if self.batch_len != 0 {
+            state.set_batch(self.batch_len)?;
+        }
+
+        let round = state.round();
+
+        emit!(VrfRequestRandomness {
+            vrf: self.vrf.key(),
+            round,
+        });
+        Ok(())
+    }
+}
@@ -1,21 +1,64 @@
 use anchor_lang::prelude::*;
 
+use crate::{
+    compact::{CompactAccountMeta, VrfCompactAccountData},
+    error::VrfError,
+};
+
 pub const RESULT_BYTE_LEN: usize = 32;
-pub const PROOF_BYTE_LEN: usize = 80;
+pub const PROOF_BYTE_LEN: usize = 81;
 pub const SEEDS_BYTE_LEN: usize = 32;
+/// Maximum number of accounts of a callback instruction.
+pub const MAX_CALLBACK_ACCOUNTS: usize = 32;
+/// Maximum size of the serialized data of a callback instruction.
+pub const MAX_CALLBACK_IX_DATA_LEN: usize = 1024;
+/// Maximum number of callback accounts resolved through an address lookup table,
+/// a transaction cannot lock more accounts anyway.
+pub const MAX_CALLBACK_LOOKUP_ACCOUNTS: usize = 64;
+/// Maximum number of slots of a batch request, see [`VrfAccountData::batch`].
+pub const MAX_BATCH_LEN: usize = 16;
+
+/// The address lookup table program.
+pub const ADDRESS_LOOKUP_TABLE_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("AddressLookupTab1e1111111111111111111111111");
+/// Size of the lookup table metadata, preceding the addresses.
+const LOOKUP_TABLE_META_SIZE: usize = 56;
 
 pub const VRF_RESULT_DISCRIMINATOR: [u8; 32] = [
     169, 181, 96, 37, 231, 213, 250, 114, 103, 201, 179, 141, 92, 38, 30, 87, 115, 210, 50, 29,
     136, 193, 41, 211, 45, 205, 112, 191, 205, 195, 2, 105,
 ];
 
+pub const VRF_PROOF_DISCRIMINATOR: [u8; 32] = [
+    121, 67, 222, 183, 134, 213, 125, 41, 50, 91, 55, 91, 150, 164, 136, 194, 202, 61, 33, 95, 223,
+    94, 94, 197, 80, 89, 64, 100, 245, 187, 226, 66,
+];
+
 #[event]
 pub struct VrfRequestRandomness {
     pub vrf: Pubkey,
+    /// The [`round`](`VrfAccountData::round`) of the request, so a stale event
+    /// of a re-armed account is not fulfilled twice.
+    pub round: u64,
+}
+
+/// Emitted when an account is upgraded to [`LAYOUT_VERSION`].
+#[event]
+pub struct VrfAccountMigrated {
+    pub vrf: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+/// Emitted when a request is cancelled, the VRF-server will not fulfill it.
+#[event]
+pub struct VrfRequestCancelled {
+    pub vrf: Pubkey,
 }
 
 #[zero_copy]
-#[repr(packed)]
+#[derive(AnchorSerialize, AnchorDeserialize)]
+#[repr(C, packed)]
 pub struct AccountMetaPacked {
     pub pubkey: Pubkey,
     pub is_signer: bool,
@@ -23,25 +66,589 @@ pub struct AccountMetaPacked {
 }
 
 #[zero_copy]
-#[repr(packed)]
+#[repr(C, packed)]
 pub struct CallbackPacked {
     /// Program ID of the callback program being invoked.
     pub program_id: Pubkey,
     /// The accounts being used in the callback instruction.
-    pub accounts: [AccountMetaPacked; 32],
+    pub accounts: [AccountMetaPacked; MAX_CALLBACK_ACCOUNTS],
     /// The number of accounts used in the callback.
-    pub accounts_len: u32,
+    pub accounts_len: u16,
+    /// Offset of the [`VrfResult`](`crate::VrfResult`) in `ix_data` plus one,
+    /// `0` for requests made before the offset was recorded, see [`CallbackOffsets`].
+    pub result_offset: u16,
     /// The serialized instruction data.
-    pub ix_data: [u8; 1024],
+    pub ix_data: [u8; MAX_CALLBACK_IX_DATA_LEN],
     /// The number of serialized bytes in the instruction data.
-    pub ix_data_len: u32,
+    pub ix_data_len: u16,
+    /// Offset of the [`VrfProof`](`crate::VrfProof`) in `ix_data` plus one,
+    /// `0` if the callback does not take the proof or for requests made before the offset was recorded.
+    pub proof_offset: u16,
+}
+
+impl From<&AccountMeta> for AccountMetaPacked {
+    fn from(meta: &AccountMeta) -> Self {
+        Self {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+impl CallbackPacked {
+    /// The accounts of the callback instruction.
+    pub fn accounts(&self) -> &[AccountMetaPacked] {
+        &self.accounts[0..self.accounts_len as usize]
+    }
+
+    /// The serialized callback instruction data, with the default
+    /// [`VrfResult`](`crate::VrfResult`) and [`VrfProof`](`crate::VrfProof`).
+    pub fn ix_data(&self) -> &[u8] {
+        &self.ix_data[0..self.ix_data_len as usize]
+    }
+
+    /// Store the callback instruction data, and record where the
+    /// [`VrfResult`](`crate::VrfResult`) and [`VrfProof`](`crate::VrfProof`) are.
+    ///
+    /// Fail if `ix_data` does not contain exactly one `VrfResult` and at most one `VrfProof`,
+    /// see [`CallbackOffsets::find`].
+    pub fn set_ix_data(&mut self, ix_data: &[u8]) -> anchor_lang::Result<()> {
+        self.set_ix_data_at(ix_data, CallbackOffsets::find(ix_data)?)
+    }
+
+    /// Same as [`set_ix_data`](`Self::set_ix_data`), with the given `offsets`.
+    pub fn set_ix_data_at(
+        &mut self,
+        ix_data: &[u8],
+        offsets: CallbackOffsets,
+    ) -> anchor_lang::Result<()> {
+        offsets.validate(ix_data.len())?;
+
+        self.ix_data_len = ix_data.len() as u16;
+        self.ix_data[0..ix_data.len()].copy_from_slice(ix_data);
+        (self.result_offset, self.proof_offset) = offsets.packed();
+
+        Ok(())
+    }
+
+    /// The stored callback, borrowed.
+    pub fn view(&self) -> CallbackRef<'_> {
+        CallbackRef::new(
+            self.program_id,
+            CallbackAccounts::Packed(self.accounts()),
+            self.ix_data(),
+            self.result_offset,
+            self.proof_offset,
+        )
+    }
+
+    /// See [`CallbackRef::result_offset`].
+    pub fn result_offset(&self) -> Option<usize> {
+        self.view().result_offset()
+    }
+
+    /// See [`CallbackRef::proof_offset`].
+    pub fn proof_offset(&self) -> Option<usize> {
+        self.view().proof_offset()
+    }
+
+    /// See [`CallbackRef::fill_ix_data`].
+    pub fn fill_ix_data(
+        &self,
+        result: &[u8; RESULT_BYTE_LEN],
+        proof: &[u8; PROOF_BYTE_LEN],
+    ) -> Option<Vec<u8>> {
+        self.view().fill_ix_data(result, proof)
+    }
+}
+
+/// A callback borrowed from a [`CallbackPacked`]
+/// or from a [compact account](`crate::compact`).
+#[derive(Clone, Copy)]
+pub struct CallbackRef<'a> {
+    /// Program ID of the callback program being invoked.
+    pub program_id: Pubkey,
+    accounts: CallbackAccounts<'a>,
+    /// The serialized instruction data.
+    pub ix_data: &'a [u8],
+    result_offset: u16,
+    proof_offset: u16,
+}
+
+/// The accounts of a [`CallbackRef`], as stored by each layout.
+#[derive(Clone, Copy)]
+pub(crate) enum CallbackAccounts<'a> {
+    Packed(&'a [AccountMetaPacked]),
+    Compact(&'a [CompactAccountMeta]),
+}
+
+impl<'a> CallbackRef<'a> {
+    pub(crate) fn new(
+        program_id: Pubkey,
+        accounts: CallbackAccounts<'a>,
+        ix_data: &'a [u8],
+        result_offset: u16,
+        proof_offset: u16,
+    ) -> Self {
+        Self {
+            program_id,
+            accounts,
+            ix_data,
+            result_offset,
+            proof_offset,
+        }
+    }
+
+    /// The accounts stored in the VRF account,
+    /// without the [`CallbackLookupPacked`] accounts.
+    pub fn accounts(&self) -> Vec<AccountMeta> {
+        match self.accounts {
+            CallbackAccounts::Packed(accounts) => accounts
+                .iter()
+                .map(|acc| AccountMeta {
+                    pubkey: acc.pubkey,
+                    is_signer: acc.is_signer,
+                    is_writable: acc.is_writable,
+                })
+                .collect(),
+            CallbackAccounts::Compact(accounts) => accounts.iter().map(AccountMeta::from).collect(),
+        }
+    }
+
+    /// Offset of the [`VrfResult`](`crate::VrfResult`) in `ix_data`.
+    ///
+    /// Requests made before the offset was recorded fall back
+    /// to the first occurrence of [`VRF_RESULT_DISCRIMINATOR`].
+    pub fn result_offset(&self) -> Option<usize> {
+        match self.result_offset {
+            0 => find_first(self.ix_data, &VRF_RESULT_DISCRIMINATOR, RESULT_BYTE_LEN),
+            offset => Some(offset as usize - 1),
+        }
+    }
+
+    /// Offset of the [`VrfProof`](`crate::VrfProof`) in `ix_data`,
+    /// `None` if the callback does not take the proof.
+    ///
+    /// Requests made before the offset was recorded fall back
+    /// to the first occurrence of [`VRF_PROOF_DISCRIMINATOR`].
+    pub fn proof_offset(&self) -> Option<usize> {
+        match (self.result_offset, self.proof_offset) {
+            (0, _) => find_first(self.ix_data, &VRF_PROOF_DISCRIMINATOR, PROOF_BYTE_LEN),
+            (_, 0) => None,
+            (_, offset) => Some(offset as usize - 1),
+        }
+    }
+
+    /// The callback instruction data with `result` and `proof` written in,
+    /// `None` if the `VrfResult` cannot be found.
+    ///
+    /// `proof` is only written if the callback take it.
+    pub fn fill_ix_data(
+        &self,
+        result: &[u8; RESULT_BYTE_LEN],
+        proof: &[u8; PROOF_BYTE_LEN],
+    ) -> Option<Vec<u8>> {
+        let mut ix_data = self.ix_data.to_vec();
+
+        let result_offset = self.result_offset()?;
+        ix_data
+            .get_mut(result_offset..result_offset + RESULT_BYTE_LEN)?
+            .copy_from_slice(result);
+
+        if let Some(proof_offset) = self.proof_offset() {
+            ix_data
+                .get_mut(proof_offset..proof_offset + PROOF_BYTE_LEN)?
+                .copy_from_slice(proof);
+        }
+
+        Some(ix_data)
+    }
+
+    /// All the accounts of the callback instruction, the stored accounts
+    /// followed by the `lookup` accounts resolved from the `lookup_table` data.
+    pub fn account_metas(
+        &self,
+        lookup: &CallbackLookupPacked,
+        lookup_table: Option<&[u8]>,
+    ) -> anchor_lang::Result<Vec<AccountMeta>> {
+        let mut metas = self.accounts();
+
+        if lookup.is_used() {
+            let data = lookup_table.ok_or_else(|| error!(VrfError::InvalidLookupTable))?;
+            metas.extend(lookup.resolve(data)?);
+        }
+
+        Ok(metas)
+    }
+}
+
+/// Where the VRF-server write the [`VrfResult`](`crate::VrfResult`) and [`VrfProof`](`crate::VrfProof`)
+/// in the callback instruction data.
+///
+/// They are stored plus one, so `0` is left to the requests made before the offsets were recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallbackOffsets {
+    pub result: usize,
+    /// `None` if the callback does not take the proof.
+    pub proof: Option<usize>,
+}
+
+impl CallbackOffsets {
+    /// The offsets of the placeholders of an anchor callback,
+    /// the default `VrfResult` and `VrfProof` serialized in `ix_data`.
+    ///
+    /// Fail if `ix_data` does not contain exactly one `VrfResult` and at most one `VrfProof`.
+    pub fn find(ix_data: &[u8]) -> anchor_lang::Result<Self> {
+        let result = match find_unique(ix_data, &VRF_RESULT_DISCRIMINATOR)? {
+            Some(offset) => offset,
+            None => {
+                return Err(invalid_callback_error(
+                    "VrfResult not found in the callback instruction data",
+                ))
+            }
+        };
+        let proof = find_unique(ix_data, &VRF_PROOF_DISCRIMINATOR)?;
+
+        Ok(Self { result, proof })
+    }
+
+    /// Check that the result and proof fit in `ix_data_len` bytes of instruction data
+    /// without overlapping.
+    pub fn validate(&self, ix_data_len: usize) -> anchor_lang::Result<()> {
+        let result = self.result..self.result.saturating_add(RESULT_BYTE_LEN);
+        if result.end > ix_data_len {
+            return Err(error!(VrfError::InvalidCallbackOffset)
+                .with_values((format!("result {result:?}"), ix_data_len)));
+        }
+
+        if let Some(proof) = self.proof {
+            let proof = proof..proof.saturating_add(PROOF_BYTE_LEN);
+            if proof.end > ix_data_len {
+                return Err(error!(VrfError::InvalidCallbackOffset)
+                    .with_values((format!("proof {proof:?}"), ix_data_len)));
+            }
+            if proof.start < result.end && result.start < proof.end {
+                return Err(error!(VrfError::InvalidCallbackOffset)
+                    .with_values((format!("result {result:?}"), format!("proof {proof:?}"))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The offsets as stored in a callback, once validated.
+    pub(crate) fn packed(&self) -> (u16, u16) {
+        (
+            self.result as u16 + 1,
+            self.proof.map_or(0, |proof| proof as u16 + 1),
+        )
+    }
+}
+
+/// Callback accounts stored as indexes in an address lookup table,
+/// appended after the [`CallbackPacked::accounts`].
+///
+/// The VRF-server resolve the table and send a v0 transaction,
+/// so a callback is not limited to [`MAX_CALLBACK_ACCOUNTS`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallbackLookup {
+    /// The address lookup table.
+    pub table: Pubkey,
+    /// Indexes in the table of the writable accounts.
+    pub writable_indexes: Vec<u8>,
+    /// Indexes in the table of the read-only accounts.
+    pub readonly_indexes: Vec<u8>,
 }
 
 #[zero_copy]
-#[repr(packed)]
-pub struct VrfAccountData {
+#[repr(C, packed)]
+pub struct CallbackLookupPacked {
+    /// The address lookup table, `Pubkey::default()` if the callback does not use one.
+    pub table: Pubkey,
+    /// Indexes in the table of the writable accounts, then of the read-only accounts.
+    pub indexes: [u8; MAX_CALLBACK_LOOKUP_ACCOUNTS],
+    pub writable_len: u8,
+    pub readonly_len: u8,
+}
+
+impl CallbackLookupPacked {
+    /// Whether the callback uses an address lookup table.
+    pub fn is_used(&self) -> bool {
+        self.table != Pubkey::default()
+    }
+
+    pub fn writable_indexes(&self) -> &[u8] {
+        &self.indexes[0..self.writable_len as usize]
+    }
+
+    pub fn readonly_indexes(&self) -> &[u8] {
+        let start = self.writable_len as usize;
+        &self.indexes[start..start + self.readonly_len as usize]
+    }
+
+    /// The accounts of the lookup table `data`, writable first.
+    ///
+    /// Lookup accounts are never signers.
+    pub fn resolve(&self, data: &[u8]) -> anchor_lang::Result<Vec<AccountMeta>> {
+        let addresses = lookup_table_addresses(data)?;
+        let resolve = |index: &u8, is_writable: bool| {
+            addresses
+                .get(*index as usize)
+                .map(|pubkey| AccountMeta {
+                    pubkey: *pubkey,
+                    is_signer: false,
+                    is_writable,
+                })
+                .ok_or_else(|| {
+                    error!(VrfError::InvalidLookupTable).with_values((*index, addresses.len()))
+                })
+        };
+
+        self.writable_indexes()
+            .iter()
+            .map(|index| resolve(index, true))
+            .chain(
+                self.readonly_indexes()
+                    .iter()
+                    .map(|index| resolve(index, false)),
+            )
+            .collect()
+    }
+}
+
+/// The addresses stored in an address lookup table account `data`.
+pub fn lookup_table_addresses(data: &[u8]) -> anchor_lang::Result<Vec<Pubkey>> {
+    // ProgramState::LookupTable, serialized as a little endian u32
+    if data.len() < LOOKUP_TABLE_META_SIZE || data[0..4] != 1u32.to_le_bytes() {
+        return Err(error!(VrfError::InvalidLookupTable));
+    }
+
+    let chunks = data[LOOKUP_TABLE_META_SIZE..].chunks_exact(32);
+    if !chunks.remainder().is_empty() {
+        return Err(error!(VrfError::InvalidLookupTable));
+    }
+
+    Ok(chunks
+        .map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// Check that a lookup table fit in a [`VrfAccountData`],
+/// without duplicated index.
+pub fn validate_callback_lookup(lookup: &CallbackLookup) -> anchor_lang::Result<()> {
+    require_keys_neq!(
+        lookup.table,
+        Pubkey::default(),
+        VrfError::InvalidLookupTable
+    );
+
+    let len = lookup.writable_indexes.len() + lookup.readonly_indexes.len();
+    if len > MAX_CALLBACK_LOOKUP_ACCOUNTS {
+        return Err(error!(VrfError::TooManyCallbackAccounts)
+            .with_values((len, MAX_CALLBACK_LOOKUP_ACCOUNTS)));
+    }
+
+    let mut seen = [false; 256];
+    for index in lookup
+        .writable_indexes
+        .iter()
+        .chain(lookup.readonly_indexes.iter())
+    {
+        if std::mem::replace(&mut seen[*index as usize], true) {
+            return Err(error!(VrfError::DuplicateCallbackAccount).with_values((*index, *index)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a request fit in a [`VrfAccountData`].
+///
+/// - `seeds` must not be empty nor zeroed, and at most [`SEEDS_BYTE_LEN`] bytes
+/// - at most [`MAX_CALLBACK_ACCOUNTS`] callback accounts, without duplicates
+/// - at most [`MAX_CALLBACK_IX_DATA_LEN`] bytes of callback instruction data
+pub fn validate_request(
+    seeds: &[u8],
+    callback_accounts: &[AccountMeta],
+    callback_ix_data: &[u8],
+) -> anchor_lang::Result<()> {
+    require!(
+        !seeds.is_empty() && seeds.iter().any(|v| *v != 0),
+        VrfError::InvalidSeeds
+    );
+
+    if seeds.len() > SEEDS_BYTE_LEN {
+        return Err(error!(VrfError::SeedsTooLong).with_values((seeds.len(), SEEDS_BYTE_LEN)));
+    }
+
+    if callback_accounts.len() > MAX_CALLBACK_ACCOUNTS {
+        return Err(error!(VrfError::TooManyCallbackAccounts)
+            .with_values((callback_accounts.len(), MAX_CALLBACK_ACCOUNTS)));
+    }
+
+    if callback_ix_data.len() > MAX_CALLBACK_IX_DATA_LEN {
+        return Err(error!(VrfError::CallbackDataTooLarge)
+            .with_values((callback_ix_data.len(), MAX_CALLBACK_IX_DATA_LEN)));
+    }
+
+    for (i, meta) in callback_accounts.iter().enumerate() {
+        if callback_accounts[..i]
+            .iter()
+            .any(|other| other.pubkey == meta.pubkey)
+        {
+            return Err(
+                error!(VrfError::DuplicateCallbackAccount).with_pubkeys((meta.pubkey, meta.pubkey))
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// First offset of `discriminator` in `ix_data` with room for a `value_len` bytes value.
+fn find_first(ix_data: &[u8], discriminator: &[u8], value_len: usize) -> Option<usize> {
+    ix_data
+        .windows(discriminator.len())
+        .position(|slice| slice == discriminator)
+        .filter(|offset| offset + value_len <= ix_data.len())
+}
+
+/// Offset of `discriminator` in `ix_data`, failing if it is found more than once.
+fn find_unique(ix_data: &[u8], discriminator: &[u8]) -> anchor_lang::Result<Option<usize>> {
+    let mut offsets = ix_data
+        .windows(discriminator.len())
+        .enumerate()
+        .filter(|(_, slice)| slice == &discriminator)
+        .map(|(offset, _)| offset);
+
+    let offset = match offsets.next() {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    if offsets.next().is_some() {
+        return Err(invalid_callback_error(
+            "VrfResult or VrfProof found more than once in the callback instruction data",
+        ));
+    }
+
+    Ok(Some(offset))
+}
+
+fn invalid_callback_error(msg: &str) -> Error {
+    msg!(msg);
+    error!(VrfError::InvalidCallback)
+}
+
+/// Current version of [`VrfStatusHeader`].
+pub const STATUS_HEADER_VERSION: u8 = 1;
+
+/// Current layout version of [`VrfAccountData`] and
+/// [`VrfCompactAccountData`](`crate::compact::VrfCompactAccountData`).
+///
+/// `0` for accounts created before the layout was versioned, read in place: the 80-byte proof slot
+/// is kept as [`legacy_proof`](`VrfAccountData::legacy_proof`), the callback lengths, stored as `u32`,
+/// left their zeroed high half to the result and proof offsets, and every other new field was carved
+/// from the zeroed reserved area, so they all read as unset.
+///
+/// - `1`: the layout is versioned.
+/// - `2`: the compact header gained the program `fields_len`, the `round` and `last_round`,
+///   and a reserved area new fields are carved from. An older compact account cannot be
+///   read in place, see [`OLDEST_COMPACT_LAYOUT_VERSION`]. The full layout is unchanged.
+pub const LAYOUT_VERSION: u8 = 2;
+
+/// The oldest layout version a compact account can be read with.
+pub const OLDEST_COMPACT_LAYOUT_VERSION: u8 = 2;
+
+/// Lifecycle of a randomness request.
+///
+/// A request starts `Pending` and ends up in exactly one of the other states.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VrfStatus {
+    /// Waiting for the oracle.
+    Pending = 1,
+    /// The oracle has supplied the result.
+    Fulfilled = 2,
+    /// The oracle did not answer in time.
+    Expired = 3,
+    /// The requester gave up on the request.
+    Cancelled = 4,
+}
+
+impl TryFrom<u8> for VrfStatus {
+    type Error = anchor_lang::error::Error;
+
+    fn try_from(value: u8) -> anchor_lang::Result<Self> {
+        match value {
+            1 => Ok(Self::Pending),
+            2 => Ok(Self::Fulfilled),
+            3 => Ok(Self::Expired),
+            4 => Ok(Self::Cancelled),
+            _ => Err(error!(VrfError::InvalidStatus).with_values((value, "1..=4"))),
+        }
+    }
+}
+
+#[zero_copy]
+#[repr(C, packed)]
+pub struct VrfStatusHeader {
+    /// [`STATUS_HEADER_VERSION`] when the header was written,
+    /// `0` for accounts requested before the header existed.
+    pub version: u8,
+    /// [`VrfStatus`] of the request.
+    pub status: u8,
+    /// The slot when the VRF round was opened.
+    pub request_slot: u64,
+    /// The unix timestamp when the request left `Pending`.
+    pub closed_timestamp: i64,
+    /// The slot when the request left `Pending`.
+    pub closed_slot: u64,
+}
+
+/// The outcome of the previous round of a re-armed VRF account.
+#[zero_copy]
+#[repr(C, packed)]
+pub struct VrfLastRound {
+    /// The round number, `0` if the account was never re-armed.
+    pub round: u64,
     pub result: crate::VrfResult,
     pub proof: [u8; PROOF_BYTE_LEN],
+}
+
+/// The slots of a batch request, each fulfilled with its own result and proof.
+#[zero_copy]
+#[repr(C, packed)]
+pub struct VrfBatchPacked {
+    /// The number of slots, `0` if the request is not a batch.
+    pub len: u8,
+    /// The number of slots fulfilled, slots are fulfilled in order.
+    pub fulfilled: u8,
+    pub results: [crate::VrfResult; MAX_BATCH_LEN],
+}
+
+/// The seeds of the `slot` of a batch request opened with `seeds`.
+pub fn batch_seeds(seeds: &[u8; SEEDS_BYTE_LEN], slot: u8) -> [u8; SEEDS_BYTE_LEN] {
+    anchor_lang::solana_program::hash::hashv(&[seeds, &[slot]]).to_bytes()
+}
+
+/// Check that a batch fit in a [`VrfAccountData`],
+/// from `1` to [`MAX_BATCH_LEN`] slots.
+pub fn validate_batch_len(len: u8) -> anchor_lang::Result<()> {
+    if len == 0 || len as usize > MAX_BATCH_LEN {
+        return Err(error!(VrfError::InvalidBatch).with_values((len, MAX_BATCH_LEN)));
+    }
+
+    Ok(())
+}
+
+#[zero_copy]
+#[repr(C, packed)]
+pub struct VrfAccountData {
+    pub result: crate::VrfResult,
+    /// The proof slot of the accounts created before the proof was verified on-chain,
+    /// only 80 bytes long. Unused since, the [`proof`](`Self::proof`) is stored after the batch.
+    pub legacy_proof: [u8; 80],
     pub seeds: [u8; SEEDS_BYTE_LEN],
 
     /// The unix timestamp when the VRF round was opened.
@@ -49,8 +656,41 @@ pub struct VrfAccountData {
 
     /// The callback that is invoked when we fulfill the request.
     pub callback: CallbackPacked,
+
+    /// Who requested the randomness, only used by the coordinator program.
+    pub requester: Pubkey,
+
+    /// The [`Oracle`](`crate::oracle::Oracle`) account expected to fulfill the request.
+    pub oracle: Pubkey,
+
+    /// Where the request is in its lifecycle, see [`status`](`Self::status`).
+    pub header: VrfStatusHeader,
+
+    /// The fee paid to the oracle when requesting, see [`Oracle::fee`](`crate::oracle::Oracle::fee`).
+    pub fee_paid: u64,
+
+    /// The callback accounts stored in an address lookup table, if any.
+    pub lookup: CallbackLookupPacked,
+
+    /// [`LAYOUT_VERSION`] when the request was opened, see [`migrate`](`Self::migrate`).
+    pub layout_version: u8,
+
+    /// Number of requests opened with this account, starting at `1`.
+    /// `0` for accounts requested before the round counter existed.
+    pub round: u64,
+
+    /// The previous round, kept when the account is re-armed by
+    /// [request_randomness](`crate::request_randomness`) once closed.
+    pub last_round: VrfLastRound,
+
+    /// The slots of a batch request, see [`fulfill_next_slot`](`Self::fulfill_next_slot`).
+    pub batch: VrfBatchPacked,
+
+    /// The proof of the `result`, see [`verify`](`Self::verify`).
+    pub proof: [u8; PROOF_BYTE_LEN],
+
     /// Reserved for future info.
-    pub _buf: [u8; 1024],
+    pub _buf: [u8; 103],
 }
 
 unsafe impl anchor_lang::__private::bytemuck::Pod for VrfAccountData {}
@@ -59,3 +699,678 @@ unsafe impl anchor_lang::__private::bytemuck::Zeroable for VrfAccountData {}
 impl anchor_lang::Discriminator for VrfAccountData {
     const DISCRIMINATOR: [u8; 8] = [101, 35, 62, 239, 103, 151, 6, 18];
 }
+
+/// The request lifecycle, shared by [`VrfAccountData`]
+/// and [`VrfCompactAccountData`](`crate::compact::VrfCompactAccountData`).
+macro_rules! impl_request_lifecycle {
+    ($account:ty, $oldest_layout_version:expr) => {
+        impl $account {
+            /// Start a new request: the seeds, the oracle and a `Pending` status.
+            pub(crate) fn open_request(
+                &mut self,
+                seeds: &[u8],
+                oracle: Pubkey,
+            ) -> anchor_lang::Result<()> {
+                self.seeds[0..seeds.len()].copy_from_slice(seeds);
+
+                let clock = Clock::get()?;
+                self.request_timestamp = clock.unix_timestamp;
+                self.header = VrfStatusHeader {
+                    version: STATUS_HEADER_VERSION,
+                    status: VrfStatus::Pending as u8,
+                    request_slot: clock.slot,
+                    closed_timestamp: 0,
+                    closed_slot: 0,
+                };
+                self.oracle = oracle;
+                self.layout_version = LAYOUT_VERSION;
+                self.round = self.round.wrapping_add(1);
+
+                Ok(())
+            }
+
+            /// Clear a closed request before a new round is opened with the same account,
+            /// moving its result and proof to [`last_round`](`Self::last_round`).
+            ///
+            /// Fail if the request is still `Pending`.
+            ///
+            /// This does NOT open the new round,
+            /// use [request_randomness](`crate::request_randomness`) instead.
+            pub fn rearm(&mut self) -> anchor_lang::Result<()> {
+                let status = self.status()?;
+                if status == VrfStatus::Pending {
+                    return Err(error!(VrfError::InvalidStatus)
+                        .with_values((format!("{status:?}"), "Fulfilled, Expired or Cancelled")));
+                }
+
+                self.last_round = crate::vrf::VrfLastRound {
+                    round: self.round,
+                    result: self.result,
+                    proof: self.proof,
+                };
+                self.result = crate::VrfResult {
+                    result: [0u8; RESULT_BYTE_LEN],
+                };
+                self.proof = [0u8; PROOF_BYTE_LEN];
+                self.seeds = [0u8; SEEDS_BYTE_LEN];
+                self.lookup = crate::vrf::CallbackLookupPacked {
+                    table: Pubkey::default(),
+                    indexes: [0u8; crate::vrf::MAX_CALLBACK_LOOKUP_ACCOUNTS],
+                    writable_len: 0,
+                    readonly_len: 0,
+                };
+                self.fee_paid = 0;
+
+                Ok(())
+            }
+
+            /// The layout version of the account, `0` for accounts created before versioning.
+            ///
+            /// Fail for a layout written by a newer sdk,
+            /// or too old to be read in place.
+            #[allow(unused_comparisons)] // every full layout can be read
+            pub fn layout_version(&self) -> anchor_lang::Result<u8> {
+                let version = self.layout_version;
+                if version > LAYOUT_VERSION {
+                    return Err(error!(VrfError::UnsupportedLayoutVersion)
+                        .with_values((version, LAYOUT_VERSION)));
+                }
+                if version < $oldest_layout_version {
+                    return Err(error!(VrfError::OutdatedLayoutVersion)
+                        .with_values((version, $oldest_layout_version)));
+                }
+
+                Ok(version)
+            }
+
+            /// Upgrade an account written by an older sdk to [`LAYOUT_VERSION`],
+            /// returning its previous version.
+            ///
+            /// This does NOT emit [`VrfAccountMigrated`](`crate::vrf::VrfAccountMigrated`),
+            /// use [migrate_account](`crate::migrate_account`) instead.
+            pub fn migrate(&mut self) -> anchor_lang::Result<u8> {
+                let version = self.layout_version()?;
+                // 0 -> 1: the fields of version 0 did not move (see `LAYOUT_VERSION`) and the new
+                // ones read as unset, only the status header must be written from the current state.
+                // When the request was opened or fulfilled is unknown, so left at `0`.
+                // 1 -> 2: only the compact header changed, an older one is refused above.
+                if self.header.version == 0 {
+                    self.header = crate::vrf::VrfStatusHeader {
+                        version: STATUS_HEADER_VERSION,
+                        status: self.status()? as u8,
+                        request_slot: 0,
+                        closed_timestamp: 0,
+                        closed_slot: 0,
+                    };
+                }
+                self.layout_version = LAYOUT_VERSION;
+                Ok(version)
+            }
+
+            /// Append the accounts of an address lookup table to the callback accounts,
+            /// after [`set_request`](`Self::set_request`).
+            pub fn set_callback_lookup(
+                &mut self,
+                lookup: &CallbackLookup,
+            ) -> anchor_lang::Result<()> {
+                validate_callback_lookup(lookup)?;
+
+                let writable_len = lookup.writable_indexes.len();
+                self.lookup.table = lookup.table;
+                self.lookup.indexes[0..writable_len].copy_from_slice(&lookup.writable_indexes);
+                self.lookup.indexes[writable_len..writable_len + lookup.readonly_indexes.len()]
+                    .copy_from_slice(&lookup.readonly_indexes);
+                self.lookup.writable_len = writable_len as u8;
+                self.lookup.readonly_len = lookup.readonly_indexes.len() as u8;
+
+                Ok(())
+            }
+
+            /// Same as [`verify`](`Self::verify`), using the VRF public key
+            /// of the [`Oracle`](`crate::oracle::Oracle`) the request is bound to.
+            ///
+            /// Example
+            /// ```ignore
+            /// let mut vrf = ctx.accounts.vrf.load_mut()?;
+            /// vrf.result = vrf_result;
+            /// vrf.proof = vrf_proof.proof;
+            /// vrf.verify_oracle(&ctx.accounts.oracle)?;
+            /// ```
+            pub fn verify_oracle(
+                &self,
+                oracle: &AccountInfo<'_>,
+            ) -> anchor_lang::Result<crate::VrfResult> {
+                let expected = self.oracle;
+                if oracle.key() != expected {
+                    return Err(error!(ErrorCode::ConstraintAddress)
+                        .with_account_name("oracle")
+                        .with_pubkeys((oracle.key(), expected)));
+                }
+
+                let oracle = crate::oracle::Oracle::load(oracle)?;
+                self.verify(&oracle.vrf_public_key.0)
+            }
+
+            /// Where the request is in its lifecycle.
+            ///
+            /// Accounts requested before the status header existed are `Fulfilled`
+            /// once the result is supplied, `Pending` otherwise.
+            pub fn status(&self) -> anchor_lang::Result<VrfStatus> {
+                self.layout_version()?;
+                if self.header.version == 0 {
+                    return Ok(if self.is_fulfilled() {
+                        VrfStatus::Fulfilled
+                    } else {
+                        VrfStatus::Pending
+                    });
+                }
+
+                VrfStatus::try_from(self.header.status)
+            }
+
+            /// The unix timestamp and slot when the request was fulfilled,
+            /// `None` if the request is not `Fulfilled` or was fulfilled before the status header existed.
+            pub fn fulfilled_at(&self) -> Option<(i64, u64)> {
+                if self.header.version == 0
+                    || self.header.status != VrfStatus::Fulfilled as u8
+                    || self.header.closed_slot == 0
+                {
+                    return None;
+                }
+
+                Some((self.header.closed_timestamp, self.header.closed_slot))
+            }
+
+            /// The slot when the request was opened,
+            /// `None` if the request was requested before the status header existed.
+            pub fn request_slot(&self) -> Option<u64> {
+                if self.header.version == 0 || self.header.request_slot == 0 {
+                    return None;
+                }
+
+                Some(self.header.request_slot)
+            }
+
+            /// Mark a `Pending` request as `Fulfilled`, recording the current timestamp and slot.
+            ///
+            /// Call it once the proof is verified.
+            ///
+            /// Example
+            /// ```ignore
+            /// let mut vrf = ctx.accounts.vrf.load_mut()?;
+            /// vrf.result = vrf_result;
+            /// vrf.proof = vrf_proof.proof;
+            /// vrf.verify_oracle(&ctx.accounts.oracle)?;
+            /// vrf.set_fulfilled()?;
+            /// ```
+            pub fn set_fulfilled(&mut self) -> anchor_lang::Result<()> {
+                self.close_request(VrfStatus::Fulfilled)
+            }
+
+            /// Whether `timeout` seconds have elapsed between `request_timestamp` and `now`.
+            pub fn is_expired(&self, timeout: i64, now: i64) -> bool {
+                now >= self.request_timestamp.saturating_add(timeout)
+            }
+
+            /// Mark a `Pending` request as `Cancelled`,
+            /// only once `timeout` seconds have elapsed since `request_timestamp`.
+            ///
+            /// This does NOT emit [`VrfRequestCancelled`](`crate::vrf::VrfRequestCancelled`),
+            /// use [cancel_request](`crate::cancel_request`) instead.
+            pub fn cancel(&mut self, timeout: i64) -> anchor_lang::Result<()> {
+                let now = Clock::get()?.unix_timestamp;
+                if !self.is_expired(timeout, now) {
+                    return Err(error!(VrfError::RequestNotExpired)
+                        .with_values((now, self.request_timestamp.saturating_add(timeout))));
+                }
+
+                self.close_request(VrfStatus::Cancelled)
+            }
+
+            /// Move a `Pending` request to its final `status`.
+            pub(crate) fn close_request(&mut self, status: VrfStatus) -> anchor_lang::Result<()> {
+                // A legacy account, read in place (see `LAYOUT_VERSION`), has no stored status,
+                // and its result is written before `set_fulfilled`, so it is still pending
+                // unless cancelled once fulfilled.
+                self.layout_version()?;
+                let current = match self.header.version {
+                    0 if status == VrfStatus::Fulfilled || !self.is_fulfilled() => {
+                        VrfStatus::Pending
+                    }
+                    _ => self.status()?,
+                };
+                if current != VrfStatus::Pending || status == VrfStatus::Pending {
+                    return Err(error!(VrfError::InvalidStatus)
+                        .with_values((format!("{current:?}"), format!("{status:?}"))));
+                }
+
+                let clock = Clock::get()?;
+                self.header.version = STATUS_HEADER_VERSION;
+                self.header.status = status as u8;
+                self.header.closed_timestamp = clock.unix_timestamp;
+                self.header.closed_slot = clock.slot;
+
+                Ok(())
+            }
+
+            /// Whether the oracle has supplied the result.
+            pub fn is_fulfilled(&self) -> bool {
+                let result = self.result.result;
+                result != [0u8; RESULT_BYTE_LEN] && result != VRF_RESULT_DISCRIMINATOR
+            }
+
+            /// Verify that `proof` is a valid VRF proof of `seeds` for the oracle's `public_key`,
+            /// and that `result` is the output of that proof.
+            ///
+            /// Example
+            /// ```ignore
+            /// let mut vrf = ctx.accounts.vrf.load_mut()?;
+            /// vrf.result = vrf_result;
+            /// vrf.proof = vrf_proof.proof;
+            /// vrf.verify(&ORACLE_PUBLIC_KEY)?;
+            /// ```
+            pub fn verify(
+                &self,
+                public_key: &[u8; crate::ecvrf::PUBLIC_KEY_BYTE_LEN],
+            ) -> anchor_lang::Result<crate::VrfResult> {
+                let result = self.result;
+
+                match crate::ecvrf::verify(public_key, &self.proof, &self.seeds) {
+                    Ok(hash) if hash == result.result => Ok(result),
+                    Ok(_) => Err(crate::invalid_proof_error(
+                        "VrfResult does not match the proof",
+                    )),
+                    Err(err) => Err(crate::invalid_proof_error(&format!("{err:?}"))),
+                }
+            }
+        }
+    };
+}
+pub(crate) use impl_request_lifecycle;
+
+impl VrfAccountData {
+    /// Fill in the seeds and callback of a new randomness request.
+    ///
+    /// This does NOT emit [`VrfRequestRandomness`],
+    /// use [request_randomness](`crate::request_randomness`) instead.
+    pub fn set_request(
+        &mut self,
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+    ) -> anchor_lang::Result<()> {
+        self.set_request_at(
+            seeds,
+            oracle,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            None,
+        )
+    }
+
+    /// Same as [`set_request`](`Self::set_request`), with the given callback `offsets`,
+    /// found in `callback_ix_data` if `None`, see [`CallbackOffsets`].
+    pub fn set_request_at(
+        &mut self,
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        offsets: Option<CallbackOffsets>,
+    ) -> anchor_lang::Result<()> {
+        validate_request(seeds, callback_accounts, callback_ix_data)?;
+        let offsets = match offsets {
+            Some(offsets) => offsets,
+            None => CallbackOffsets::find(callback_ix_data)?,
+        };
+
+        self.open_request(seeds, oracle)?;
+        self.callback.program_id = callback_program_id;
+
+        self.callback.accounts_len = callback_accounts.len() as u16;
+        for (i, meta) in callback_accounts.iter().enumerate() {
+            self.callback.accounts[i] = AccountMetaPacked::from(meta);
+        }
+
+        self.callback.set_ix_data_at(callback_ix_data, offsets)?;
+        self.batch = VrfBatchPacked {
+            len: 0,
+            fulfilled: 0,
+            results: [crate::VrfResult {
+                result: [0u8; RESULT_BYTE_LEN],
+            }; MAX_BATCH_LEN],
+        };
+
+        Ok(())
+    }
+
+    /// Turn the request into a batch of `len` slots, after [`set_request`](`Self::set_request`).
+    ///
+    /// The VRF-server fulfill each slot in its own transaction, verifying a proof taking
+    /// most of the compute budget, invoking the callback once per slot in slot order,
+    /// the callback store each result with [`fulfill_next_slot`](`Self::fulfill_next_slot`).
+    pub fn set_batch(&mut self, len: u8) -> anchor_lang::Result<()> {
+        validate_batch_len(len)?;
+        self.batch.len = len;
+        self.batch.fulfilled = 0;
+
+        Ok(())
+    }
+
+    /// The number of slots of a batch request, `0` if the request is not a batch.
+    pub fn batch_len(&self) -> u8 {
+        self.batch.len
+    }
+
+    /// The number of slots of a batch request already fulfilled, the next one is fulfilled next.
+    pub fn batch_fulfilled(&self) -> u8 {
+        self.batch.fulfilled
+    }
+
+    /// The seeds of `slot`, `None` if the request has no such slot.
+    pub fn slot_seeds(&self, slot: u8) -> Option<[u8; SEEDS_BYTE_LEN]> {
+        (slot < self.batch.len).then(|| batch_seeds(&self.seeds, slot))
+    }
+
+    /// The result of `slot`, `None` until the slot is fulfilled.
+    pub fn slot_result(&self, slot: u8) -> Option<crate::VrfResult> {
+        (slot < self.batch.fulfilled).then(|| self.batch.results[slot as usize])
+    }
+
+    /// Verify `result` and `proof` against the seeds of the next slot to fulfill
+    /// and the [`Oracle`](`crate::oracle::Oracle`) the request is bound to, then store the result.
+    ///
+    /// The request is `Fulfilled` with its last slot. Returns the slot and its result.
+    ///
+    /// Example
+    /// ```ignore
+    /// let mut vrf = ctx.accounts.vrf.load_mut()?;
+    /// let (slot, result) = vrf.fulfill_next_slot(&ctx.accounts.oracle, vrf_result, &vrf_proof.proof)?;
+    /// let spin = result.random(0..=9)?;
+    /// ```
+    pub fn fulfill_next_slot(
+        &mut self,
+        oracle: &AccountInfo<'_>,
+        result: crate::VrfResult,
+        proof: &[u8; PROOF_BYTE_LEN],
+    ) -> anchor_lang::Result<(u8, crate::VrfResult)> {
+        let status = self.status()?;
+        let slot = self.batch.fulfilled;
+        if status != VrfStatus::Pending || slot >= self.batch.len {
+            return Err(error!(VrfError::InvalidStatus)
+                .with_values((format!("{status:?} slot {slot}"), self.batch.len)));
+        }
+
+        let expected = self.oracle;
+        if oracle.key() != expected {
+            return Err(error!(ErrorCode::ConstraintAddress)
+                .with_account_name("oracle")
+                .with_pubkeys((oracle.key(), expected)));
+        }
+        let public_key = crate::oracle::Oracle::load(oracle)?.vrf_public_key.0;
+        match crate::ecvrf::verify(&public_key, proof, &batch_seeds(&self.seeds, slot)) {
+            Ok(hash) if hash == result.result => {}
+            Ok(_) => {
+                return Err(crate::invalid_proof_error(
+                    "VrfResult does not match the proof",
+                ))
+            }
+            Err(err) => return Err(crate::invalid_proof_error(&format!("{err:?}"))),
+        }
+
+        self.batch.results[slot as usize] = result;
+        self.batch.fulfilled = slot + 1;
+        if self.batch.fulfilled == self.batch.len {
+            self.result = result;
+            self.proof = *proof;
+            self.set_fulfilled()?;
+        }
+
+        Ok((slot, result))
+    }
+
+    /// All the accounts of the callback instruction.
+    ///
+    /// `lookup_table` is the data of the [`lookup`](`Self::lookup`) table account,
+    /// required only if the callback uses one.
+    pub fn callback_account_metas(
+        &self,
+        lookup_table: Option<&[u8]>,
+    ) -> anchor_lang::Result<Vec<AccountMeta>> {
+        self.callback
+            .view()
+            .account_metas(&self.lookup, lookup_table)
+    }
+}
+
+impl_request_lifecycle!(VrfAccountData, 0);
+
+/// The data layout of a VRF account, [`VrfAccountData`]
+/// or [`VrfCompactAccountData`](`crate::compact::VrfCompactAccountData`).
+pub trait VrfLayout: anchor_lang::Discriminator + Sized {
+    /// Fill in a new randomness request, with its callback lookup table and the fee paid.
+    ///
+    /// `callback_data` is the account data following the state and its `fields_len` bytes
+    /// of program fields, where a compact account store its callback.
+    /// The `callback_offsets` are found in `callback_ix_data` if `None`, see [`CallbackOffsets`].
+    #[allow(clippy::too_many_arguments)]
+    fn init_request(
+        &mut self,
+        callback_data: &mut [u8],
+        fields_len: usize,
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        callback_offsets: Option<CallbackOffsets>,
+        lookup: Option<&CallbackLookup>,
+        fee_paid: u64,
+    ) -> anchor_lang::Result<()>;
+
+    /// Clear a closed request before a new round, see [`VrfAccountData::round`].
+    fn rearm_request(&mut self) -> anchor_lang::Result<()>;
+
+    /// See [`VrfAccountData::round`].
+    fn round(&self) -> u64;
+
+    /// See [`VrfAccountData::set_batch`], only a full account can hold a batch.
+    fn set_batch(&mut self, len: u8) -> anchor_lang::Result<()>;
+
+    /// See [`VrfAccountData::cancel`].
+    fn cancel_request(&mut self, timeout: i64) -> anchor_lang::Result<()>;
+
+    /// See [`VrfAccountData::migrate`].
+    fn migrate_layout(&mut self) -> anchor_lang::Result<u8>;
+}
+
+impl VrfLayout for VrfAccountData {
+    fn init_request(
+        &mut self,
+        _callback_data: &mut [u8],
+        _fields_len: usize,
+        seeds: &[u8],
+        oracle: Pubkey,
+        callback_program_id: Pubkey,
+        callback_accounts: &[AccountMeta],
+        callback_ix_data: &[u8],
+        callback_offsets: Option<CallbackOffsets>,
+        lookup: Option<&CallbackLookup>,
+        fee_paid: u64,
+    ) -> anchor_lang::Result<()> {
+        self.set_request_at(
+            seeds,
+            oracle,
+            callback_program_id,
+            callback_accounts,
+            callback_ix_data,
+            callback_offsets,
+        )?;
+        if let Some(lookup) = lookup {
+            self.set_callback_lookup(lookup)?;
+        }
+        self.fee_paid = fee_paid;
+
+        Ok(())
+    }
+
+    fn rearm_request(&mut self) -> anchor_lang::Result<()> {
+        self.rearm()
+    }
+
+    fn round(&self) -> u64 {
+        self.round
+    }
+
+    fn set_batch(&mut self, len: u8) -> anchor_lang::Result<()> {
+        VrfAccountData::set_batch(self, len)
+    }
+
+    fn cancel_request(&mut self, timeout: i64) -> anchor_lang::Result<()> {
+        self.cancel(timeout)
+    }
+
+    fn migrate_layout(&mut self) -> anchor_lang::Result<u8> {
+        self.migrate()
+    }
+}
+
+/// A VRF account of either layout, read in place from its data.
+///
+/// Both layouts share the [`VrfAccountData`] discriminator,
+/// an account smaller than [`ACCOUNT_SIZE`](`crate::ACCOUNT_SIZE`) is a
+/// [compact account](`crate::compact`).
+#[derive(Clone, Copy)]
+pub enum VrfAccountView<'a> {
+    Full(&'a VrfAccountData),
+    Compact(&'a VrfCompactAccountData, CallbackRef<'a>),
+}
+
+impl<'a> VrfAccountView<'a> {
+    /// Read a VRF account from its whole `data`, discriminator included.
+    ///
+    /// Fail for a layout written by a newer sdk, see [`LAYOUT_VERSION`].
+    pub fn from_account_data(data: &'a [u8]) -> anchor_lang::Result<Self> {
+        use anchor_lang::{Discriminator, __private::bytemuck};
+
+        if data.len() < 8 {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if data[0..8] != VrfAccountData::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+
+        // a newer layout, or an outdated compact one, may not be read as this one
+        let view = if data.len() >= crate::ACCOUNT_SIZE {
+            let vrf: &VrfAccountData = bytemuck::from_bytes(&data[8..crate::ACCOUNT_SIZE]);
+            vrf.layout_version()?;
+            Self::Full(vrf)
+        } else {
+            let (vrf, callback_data) = VrfCompactAccountData::split(&data[8..])?;
+            vrf.layout_version()?;
+            Self::Compact(vrf, vrf.callback(callback_data)?)
+        };
+
+        Ok(view)
+    }
+
+    /// See [`VrfAccountData::layout_version`].
+    pub fn layout_version(&self) -> anchor_lang::Result<u8> {
+        match self {
+            Self::Full(vrf) => vrf.layout_version(),
+            Self::Compact(vrf, _) => vrf.layout_version(),
+        }
+    }
+
+    pub fn seeds(&self) -> [u8; SEEDS_BYTE_LEN] {
+        match self {
+            Self::Full(vrf) => vrf.seeds,
+            Self::Compact(vrf, _) => vrf.seeds,
+        }
+    }
+
+    pub fn requester(&self) -> Pubkey {
+        match self {
+            Self::Full(vrf) => vrf.requester,
+            Self::Compact(vrf, _) => vrf.requester,
+        }
+    }
+
+    /// See [`VrfAccountData::batch_len`], `0` for a compact account.
+    pub fn batch_len(&self) -> u8 {
+        match self {
+            Self::Full(vrf) => vrf.batch_len(),
+            Self::Compact(..) => 0,
+        }
+    }
+
+    /// See [`VrfAccountData::batch_fulfilled`], `0` for a compact account.
+    pub fn batch_fulfilled(&self) -> u8 {
+        match self {
+            Self::Full(vrf) => vrf.batch_fulfilled(),
+            Self::Compact(..) => 0,
+        }
+    }
+
+    pub fn round(&self) -> u64 {
+        match self {
+            Self::Full(vrf) => vrf.round,
+            Self::Compact(vrf, _) => vrf.round,
+        }
+    }
+
+    pub fn last_round(&self) -> VrfLastRound {
+        match self {
+            Self::Full(vrf) => vrf.last_round,
+            Self::Compact(vrf, _) => vrf.last_round,
+        }
+    }
+
+    pub fn oracle(&self) -> Pubkey {
+        match self {
+            Self::Full(vrf) => vrf.oracle,
+            Self::Compact(vrf, _) => vrf.oracle,
+        }
+    }
+
+    pub fn fee_paid(&self) -> u64 {
+        match self {
+            Self::Full(vrf) => vrf.fee_paid,
+            Self::Compact(vrf, _) => vrf.fee_paid,
+        }
+    }
+
+    pub fn lookup(&self) -> CallbackLookupPacked {
+        match self {
+            Self::Full(vrf) => vrf.lookup,
+            Self::Compact(vrf, _) => vrf.lookup,
+        }
+    }
+
+    /// See [`VrfAccountData::status`].
+    pub fn status(&self) -> anchor_lang::Result<VrfStatus> {
+        match self {
+            Self::Full(vrf) => vrf.status(),
+            Self::Compact(vrf, _) => vrf.status(),
+        }
+    }
+
+    pub fn callback(&self) -> CallbackRef<'a> {
+        match *self {
+            Self::Full(vrf) => vrf.callback.view(),
+            Self::Compact(_, callback) => callback,
+        }
+    }
+
+    /// See [`VrfAccountData::callback_account_metas`].
+    pub fn callback_account_metas(
+        &self,
+        lookup_table: Option<&[u8]>,
+    ) -> anchor_lang::Result<Vec<AccountMeta>> {
+        self.callback().account_metas(&self.lookup(), lookup_table)
+    }
+}
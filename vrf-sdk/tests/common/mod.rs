@@ -0,0 +1,152 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use anchor_lang::{
+    __private::bytemuck,
+    prelude::*,
+    solana_program::{
+        entrypoint::SUCCESS,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+    },
+    Discriminator,
+};
+use vrf_sdk::{
+    vrf::{
+        VrfAccountData, VrfStatus, VrfStatusHeader, STATUS_HEADER_VERSION, VRF_RESULT_DISCRIMINATOR,
+    },
+    VrfError,
+};
+
+/// A status header written at slot 10, closed at slot 42 unless `Pending`.
+pub fn header(status: VrfStatus) -> VrfStatusHeader {
+    VrfStatusHeader {
+        version: STATUS_HEADER_VERSION,
+        status: status as u8,
+        request_slot: 10,
+        closed_timestamp: 1_700_000_000,
+        closed_slot: 42,
+    }
+}
+
+/// The code of an anchor error, either a [`VrfError`] or an anchor `ErrorCode`.
+pub fn error_code<T>(result: anchor_lang::Result<T>) -> u32 {
+    match result {
+        Err(Error::AnchorError(e)) => e.error_code_number,
+        Err(err) => panic!("unexpected error: {err:?}"),
+        Ok(_) => panic!("unexpected success"),
+    }
+}
+
+/// Same as [`error_code`], for an error expected to be a [`VrfError`].
+pub fn vrf_error<T>(result: anchor_lang::Result<T>) -> VrfError {
+    let code = error_code(result);
+    VrfError::from_code(code).unwrap_or_else(|| panic!("not a vrf error: {code}"))
+}
+
+/// The code of a native program error.
+pub fn custom_code(result: std::result::Result<(), ProgramError>) -> u32 {
+    match result {
+        Err(ProgramError::Custom(code)) => code,
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+/// The `Clock` supplied by [`stub_clock`].
+pub const CLOCK_SLOT: u64 = 42;
+pub const CLOCK_TIMESTAMP: i64 = 1_700_000_000;
+
+struct ClockStubs;
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: CLOCK_SLOT,
+            unix_timestamp: CLOCK_TIMESTAMP,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+}
+
+/// Supply the `Clock` sysvar read when a request is closed, unsupported off-chain otherwise.
+pub fn stub_clock() {
+    set_syscall_stubs(Box::new(ClockStubs));
+}
+
+/// The layout of a callback account before the layout was versioned.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct BaselineAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// The layout of a callback before the layout was versioned.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct BaselineCallback {
+    pub program_id: Pubkey,
+    pub accounts: [BaselineAccountMeta; 32],
+    pub accounts_len: u32,
+    pub ix_data: [u8; 1024],
+    pub ix_data_len: u32,
+}
+
+/// The layout of a VRF account before the layout was versioned, as deployed.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct BaselineVrfAccountData {
+    pub result: [u8; 32],
+    pub proof: [u8; 80],
+    pub seeds: [u8; 32],
+    pub request_timestamp: i64,
+    pub callback: BaselineCallback,
+    pub _buf: [u8; 1024],
+}
+
+unsafe impl bytemuck::Zeroable for BaselineVrfAccountData {}
+unsafe impl bytemuck::Pod for BaselineVrfAccountData {}
+
+/// The account data of a request made before the layout was versioned,
+/// with a callback taking the result after an 8-byte discriminator and a `u64` argument.
+pub fn baseline_account(
+    seeds: [u8; 32],
+    callback_program: Pubkey,
+    accounts: &[AccountMeta],
+) -> Vec<u8> {
+    let mut vrf = <BaselineVrfAccountData as bytemuck::Zeroable>::zeroed();
+    vrf.seeds = seeds;
+    vrf.request_timestamp = 1_600_000_000;
+    vrf.proof = [9u8; 80];
+
+    let mut ix_data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    ix_data.extend_from_slice(&7u64.to_le_bytes());
+    ix_data.extend_from_slice(&VRF_RESULT_DISCRIMINATOR);
+    vrf.callback.program_id = callback_program;
+    vrf.callback.accounts_len = accounts.len() as u32;
+    for (i, meta) in accounts.iter().enumerate() {
+        vrf.callback.accounts[i] = BaselineAccountMeta {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        };
+    }
+    vrf.callback.ix_data[0..ix_data.len()].copy_from_slice(&ix_data);
+    vrf.callback.ix_data_len = ix_data.len() as u32;
+
+    let mut data = VrfAccountData::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&vrf));
+    data
+}
+
+/// The offset of a field in a struct value.
+#[macro_export]
+macro_rules! offset_of {
+    ($value:expr, $($field:ident).+) => {{
+        let value = &$value;
+        std::ptr::addr_of!(value.$($field).+) as usize - value as *const _ as usize
+    }};
+}
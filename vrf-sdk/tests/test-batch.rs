@@ -0,0 +1,109 @@
+use anchor_lang::{__private::bytemuck::Zeroable, error::ErrorCode, prelude::*};
+use vrf_sdk::{
+    compact::VrfCompactAccountData,
+    vrf::{
+        batch_seeds, VrfAccountData, VrfLayout, VrfStatus, VrfStatusHeader, MAX_BATCH_LEN,
+        STATUS_HEADER_VERSION,
+    },
+    VrfError, VrfResult,
+};
+
+mod common;
+use common::error_code;
+
+fn pending_batch(len: u8) -> VrfAccountData {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.seeds = [5u8; 32];
+    vrf.header = VrfStatusHeader {
+        version: STATUS_HEADER_VERSION,
+        status: VrfStatus::Pending as u8,
+        request_slot: 10,
+        closed_timestamp: 0,
+        closed_slot: 0,
+    };
+    vrf.set_batch(len).unwrap();
+    vrf
+}
+
+#[test]
+fn test_batch_len() {
+    let mut vrf = VrfAccountData::zeroed();
+    assert_eq!(vrf.batch_len(), 0);
+    assert_eq!(vrf.slot_seeds(0), None);
+
+    for len in [0, MAX_BATCH_LEN as u8 + 1] {
+        assert_eq!(
+            error_code(vrf.set_batch(len)),
+            u32::from(VrfError::InvalidBatch)
+        );
+    }
+    vrf.set_batch(MAX_BATCH_LEN as u8).unwrap();
+    assert_eq!(vrf.batch_len() as usize, MAX_BATCH_LEN);
+
+    // no room for the results in a compact account
+    let mut compact = VrfCompactAccountData::zeroed();
+    assert_eq!(
+        error_code(VrfLayout::set_batch(&mut compact, 2)),
+        u32::from(VrfError::InvalidBatch)
+    );
+}
+
+#[test]
+fn test_slot_seeds() {
+    let vrf = pending_batch(3);
+    let seeds = vrf.seeds;
+
+    let slots = (0..3)
+        .map(|slot| vrf.slot_seeds(slot).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(slots[1], batch_seeds(&seeds, 1));
+    assert_ne!(slots[0], slots[1]);
+    assert_ne!(slots[1], slots[2]);
+    assert_ne!(slots[0], seeds);
+    assert_eq!(vrf.slot_seeds(3), None);
+
+    // not fulfilled yet
+    assert!(vrf.slot_result(0).is_none());
+    assert_eq!(vrf.batch_fulfilled(), 0);
+}
+
+#[test]
+fn test_fulfill_next_slot_checks() {
+    let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut lamports = 0;
+    let mut data = vec![];
+    let oracle = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+
+    // bound to another oracle
+    let mut vrf = pending_batch(2);
+    assert_eq!(
+        error_code(vrf.fulfill_next_slot(&oracle, VrfResult::default(), &[0u8; 81])),
+        ErrorCode::ConstraintAddress as u32
+    );
+
+    // every slot is fulfilled
+    vrf.oracle = key;
+    vrf.batch.fulfilled = 2;
+    assert_eq!(
+        error_code(vrf.fulfill_next_slot(&oracle, VrfResult::default(), &[0u8; 81])),
+        u32::from(VrfError::InvalidStatus)
+    );
+
+    // not a batch
+    let mut vrf = pending_batch(1);
+    vrf.batch.len = 0;
+    vrf.oracle = key;
+    assert_eq!(
+        error_code(vrf.fulfill_next_slot(&oracle, VrfResult::default(), &[0u8; 81])),
+        u32::from(VrfError::InvalidStatus)
+    );
+}
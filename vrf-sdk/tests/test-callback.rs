@@ -0,0 +1,129 @@
+use anchor_lang::{AnchorSerialize, __private::bytemuck::Zeroable};
+use vrf_sdk::{
+    vrf::{CallbackOffsets, CallbackPacked, VrfAccountData, PROOF_BYTE_LEN},
+    VrfError, VrfProof, VrfResult,
+};
+
+mod common;
+use common::vrf_error;
+
+const SIGHASH: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+/// Serialize the callback instruction data the way anchor does
+fn ix_data<T: AnchorSerialize>(args: T) -> Vec<u8> {
+    let mut data = SIGHASH.to_vec();
+    args.serialize(&mut data).unwrap();
+    data
+}
+
+fn empty_callback() -> CallbackPacked {
+    VrfAccountData::zeroed().callback
+}
+
+fn callback(ix_data: &[u8]) -> anchor_lang::Result<CallbackPacked> {
+    let mut callback = empty_callback();
+    callback.set_ix_data(ix_data)?;
+    Ok(callback)
+}
+
+#[test]
+fn test_offsets_recorded() {
+    let data = ix_data((7u64, VrfResult::default(), 9u16, VrfProof::default()));
+    let callback = callback(&data).unwrap();
+
+    assert_eq!(callback.ix_data(), &data[..]);
+    assert_eq!(callback.result_offset(), Some(16));
+    assert_eq!(callback.proof_offset(), Some(16 + 32 + 2));
+
+    let result = [42u8; 32];
+    let proof = [7u8; PROOF_BYTE_LEN];
+    let filled = callback.fill_ix_data(&result, &proof).unwrap();
+    assert_eq!(
+        filled,
+        ix_data((7u64, VrfResult { result }, 9u16, VrfProof { proof }))
+    );
+}
+
+#[test]
+fn test_proof_is_optional() {
+    let data = ix_data((VrfResult::default(), 9u16));
+    let callback = callback(&data).unwrap();
+
+    assert_eq!(callback.result_offset(), Some(8));
+    assert_eq!(callback.proof_offset(), None);
+
+    let filled = callback
+        .fill_ix_data(&[42u8; 32], &[7u8; PROOF_BYTE_LEN])
+        .unwrap();
+    assert_eq!(filled, ix_data(([42u8; 32], 9u16)));
+}
+
+#[test]
+fn test_invalid_callbacks() {
+    // no VrfResult
+    assert!(callback(&ix_data((1u64, 2u64))).is_err());
+    // two VrfResult
+    assert!(callback(&ix_data((VrfResult::default(), VrfResult::default()))).is_err());
+    // two VrfProof
+    assert!(callback(&ix_data((
+        VrfResult::default(),
+        VrfProof::default(),
+        VrfProof::default()
+    )))
+    .is_err());
+    // truncated VrfProof
+    let mut data = ix_data((VrfResult::default(), VrfProof::default()));
+    data.truncate(data.len() - 1);
+    assert!(callback(&data).is_err());
+}
+
+#[test]
+fn test_legacy_offsets_are_scanned() {
+    // requests made before the offsets were recorded only have the data
+    let data = ix_data((3u8, VrfResult::default(), VrfProof::default()));
+    let mut callback = empty_callback();
+    callback.ix_data[..data.len()].copy_from_slice(&data);
+    callback.ix_data_len = data.len() as u16;
+
+    assert_eq!(callback.result_offset(), Some(9));
+    assert_eq!(callback.proof_offset(), Some(9 + 32));
+
+    callback.ix_data[9..41].copy_from_slice(&[0u8; 32]);
+    assert_eq!(callback.result_offset(), None);
+    assert!(callback
+        .fill_ix_data(&[1u8; 32], &[1u8; PROOF_BYTE_LEN])
+        .is_none());
+}
+
+#[test]
+fn test_offsets_as_given() {
+    // a native callback, the result first and no placeholder
+    let data = [9u8; 32 + 1 + PROOF_BYTE_LEN];
+    let offsets = CallbackOffsets {
+        result: 0,
+        proof: Some(33),
+    };
+    let mut callback = empty_callback();
+    callback.set_ix_data_at(&data, offsets).unwrap();
+    assert_eq!(callback.result_offset(), Some(0));
+    assert_eq!(callback.proof_offset(), Some(33));
+
+    let filled = callback
+        .fill_ix_data(&[1u8; 32], &[2u8; PROOF_BYTE_LEN])
+        .unwrap();
+    assert_eq!(&filled[0..32], &[1u8; 32]);
+    assert_eq!(filled[32], 9);
+    assert_eq!(&filled[33..], &[2u8; PROOF_BYTE_LEN]);
+
+    let invalid = |result, proof| {
+        vrf_error(CallbackOffsets { result, proof }.validate(data.len()))
+            == VrfError::InvalidCallbackOffset
+    };
+    // out of the instruction data
+    assert!(invalid(data.len() - 31, None));
+    assert!(invalid(0, Some(34)));
+    assert!(invalid(usize::MAX, None));
+    // overlapping
+    assert!(invalid(0, Some(31)));
+    assert!(invalid(40, Some(0)));
+}
@@ -0,0 +1,147 @@
+use std::mem::size_of;
+
+use anchor_lang::{
+    prelude::*, solana_program::instruction::AccountMeta, AccountDeserialize, Discriminator,
+};
+use vrf_sdk::{
+    compact::{account_size, CompactAccountMeta, VrfCompactAccountData, HEADER_SIZE},
+    declare_compact_vrf_state,
+    vrf::{
+        VrfAccountData, VrfAccountView, VrfLayout, LAYOUT_VERSION, MAX_CALLBACK_ACCOUNTS,
+        MAX_CALLBACK_IX_DATA_LEN, PROOF_BYTE_LEN,
+    },
+    VrfError, VrfProof, VrfResult, ACCOUNT_SIZE,
+};
+
+mod common;
+use common::vrf_error;
+
+declare_id!("3gfec8ANuaWzkNhAR5QRjUvGqUjMYLJ3YnSVhgMkugqv");
+
+declare_compact_vrf_state!(CompactState);
+
+/// Serialize the callback instruction data the way anchor does
+fn ix_data() -> Vec<u8> {
+    let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    (7u64, VrfResult::default(), VrfProof::default())
+        .serialize(&mut data)
+        .unwrap();
+    data
+}
+
+/// A compact account holding a callback with the given accounts
+fn compact_account(program_id: Pubkey, accounts: &[AccountMeta]) -> Vec<u8> {
+    let ix_data = ix_data();
+    let mut data = vec![0u8; account_size(accounts.len(), ix_data.len())];
+    data[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+
+    let (vrf, callback_data) = VrfCompactAccountData::split_mut(&mut data[8..]).unwrap();
+    vrf.layout_version = LAYOUT_VERSION;
+    vrf.set_callback(callback_data, program_id, accounts, &ix_data)
+        .unwrap();
+    data
+}
+
+#[test]
+fn test_account_size() {
+    assert_eq!(size_of::<CompactAccountMeta>(), 34);
+    assert_eq!(account_size(0, 0), HEADER_SIZE);
+    assert_eq!(
+        account_size(2, 10),
+        HEADER_SIZE + 2 * size_of::<CompactAccountMeta>() + 10
+    );
+    assert!(account_size(MAX_CALLBACK_ACCOUNTS, MAX_CALLBACK_IX_DATA_LEN) < ACCOUNT_SIZE);
+    assert_eq!(size_of::<CompactState>() + 8, HEADER_SIZE);
+}
+
+#[test]
+fn test_compact_callback() {
+    let program_id = Pubkey::new_unique();
+    let accounts = [
+        AccountMeta::new(Pubkey::new_unique(), true),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+    ];
+    let data = compact_account(program_id, &accounts);
+
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert!(matches!(view, VrfAccountView::Compact(..)));
+
+    let callback = view.callback();
+    assert_eq!(callback.program_id, program_id);
+    assert_eq!(callback.ix_data, &ix_data()[..]);
+    assert_eq!(callback.result_offset(), Some(16));
+    assert_eq!(callback.proof_offset(), Some(48));
+
+    let metas = view.callback_account_metas(None).unwrap();
+    assert_eq!(metas, accounts);
+
+    let filled = callback
+        .fill_ix_data(&[42u8; 32], &[7u8; PROOF_BYTE_LEN])
+        .unwrap();
+    assert_eq!(&filled[16..48], &[42u8; 32]);
+    assert_eq!(&filled[48..], &[7u8; PROOF_BYTE_LEN]);
+
+    // the header is read by AccountLoader as usual
+    let state = CompactState::try_deserialize(&mut &data[..]).unwrap();
+    let accounts_len = state.callback.accounts_len;
+    assert_eq!(accounts_len, 2);
+}
+
+#[test]
+fn test_full_account_view() {
+    let mut data = vec![0u8; ACCOUNT_SIZE];
+    data[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert!(matches!(view, VrfAccountView::Full(..)));
+    assert!(view.callback().accounts().is_empty());
+}
+
+#[test]
+fn test_invalid_compact_account() {
+    let accounts = [AccountMeta::new(Pubkey::new_unique(), false)];
+    let data = compact_account(Pubkey::new_unique(), &accounts);
+
+    // not a vrf account
+    let mut other = data.clone();
+    other[0] = 0;
+    assert!(VrfAccountView::from_account_data(&other).is_err());
+
+    // truncated callback
+    assert!(VrfAccountView::from_account_data(&data[..data.len() - 1]).is_err());
+
+    // not enough room for the callback
+    let mut small = vec![0u8; account_size(accounts.len(), 0)];
+    let (vrf, callback_data) = VrfCompactAccountData::split_mut(&mut small[8..]).unwrap();
+    assert!(vrf
+        .set_callback(callback_data, Pubkey::new_unique(), &accounts, &ix_data())
+        .is_err());
+}
+
+#[test]
+fn test_oversized_compact_account() {
+    let accounts = [AccountMeta::new(Pubkey::new_unique(), false)];
+    let init = |data: &mut [u8]| {
+        let (vrf, callback_data) = VrfCompactAccountData::split_mut(&mut data[8..]).unwrap();
+        vrf.init_request(
+            callback_data,
+            0,
+            &[1u8; 32],
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &accounts,
+            &ix_data(),
+            None,
+            None,
+            0,
+        )
+    };
+
+    // would be read as a full account
+    let mut data = vec![0u8; ACCOUNT_SIZE];
+    assert_eq!(vrf_error(init(&mut data)), VrfError::InvalidAccountSize);
+
+    common::stub_clock();
+    let mut data = vec![0u8; ACCOUNT_SIZE - 1];
+    init(&mut data).unwrap();
+}
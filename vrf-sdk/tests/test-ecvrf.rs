@@ -0,0 +1,90 @@
+use vrf_sdk::ecvrf::{self, EcvrfError};
+
+// Public key derived from the `vrf-private-key` in vrf-server.toml
+const PUBLIC_KEY: [u8; 33] = [
+    2, 117, 239, 189, 171, 45, 65, 198, 206, 43, 31, 13, 216, 237, 11, 160, 173, 73, 238, 246, 180,
+    225, 56, 167, 63, 243, 241, 227, 157, 214, 44, 102, 134,
+];
+
+// Proof of the seeds [1, 2, ..., 32]
+const PROOF: [u8; 81] = [
+    3, 161, 82, 37, 217, 124, 147, 166, 200, 213, 151, 25, 109, 140, 25, 6, 38, 115, 163, 117, 103,
+    61, 175, 65, 28, 33, 134, 212, 132, 5, 156, 112, 148, 106, 48, 235, 1, 150, 7, 149, 46, 153,
+    111, 255, 195, 38, 154, 198, 162, 41, 193, 16, 171, 126, 54, 218, 52, 88, 195, 178, 48, 0, 109,
+    227, 194, 142, 31, 222, 68, 10, 1, 55, 245, 106, 128, 184, 65, 198, 173, 6, 205,
+];
+
+const HASH: [u8; 32] = [
+    54, 215, 155, 146, 52, 160, 99, 48, 32, 132, 38, 179, 122, 71, 82, 189, 209, 137, 177, 35, 133,
+    96, 45, 128, 249, 101, 65, 43, 124, 145, 110, 18,
+];
+
+fn seeds() -> Vec<u8> {
+    (1..=32).collect()
+}
+
+#[test]
+fn test_verify() {
+    assert_eq!(ecvrf::verify(&PUBLIC_KEY, &PROOF, &seeds()), Ok(HASH));
+    assert_eq!(ecvrf::proof_to_hash(&PROOF), Ok(HASH));
+}
+
+#[test]
+fn test_verify_wrong_seeds() {
+    let mut seeds = seeds();
+    seeds[0] = 0;
+    assert_eq!(
+        ecvrf::verify(&PUBLIC_KEY, &PROOF, &seeds),
+        Err(EcvrfError::InvalidProof)
+    );
+}
+
+#[test]
+fn test_verify_wrong_public_key() {
+    // The generator point of secp256k1
+    let mut public_key = [0u8; 33];
+    public_key[0] = 2;
+    public_key[1..].copy_from_slice(&[
+        0x79, 0xBE, 0x66, 0x7E, 0xF9, 0xDC, 0xBB, 0xAC, 0x55, 0xA0, 0x62, 0x95, 0xCE, 0x87, 0x0B,
+        0x07, 0x02, 0x9B, 0xFC, 0xDB, 0x2D, 0xCE, 0x28, 0xD9, 0x59, 0xF2, 0x81, 0x5B, 0x16, 0xF8,
+        0x17, 0x98,
+    ]);
+    assert_eq!(
+        ecvrf::verify(&public_key, &PROOF, &seeds()),
+        Err(EcvrfError::InvalidProof)
+    );
+
+    let mut public_key = PUBLIC_KEY;
+    public_key[0] = 4;
+    assert_eq!(
+        ecvrf::verify(&public_key, &PROOF, &seeds()),
+        Err(EcvrfError::InvalidPublicKey)
+    );
+}
+
+#[test]
+fn test_verify_tampered_proof() {
+    // tamper with c
+    let mut proof = PROOF;
+    proof[40] ^= 1;
+    assert_eq!(
+        ecvrf::verify(&PUBLIC_KEY, &proof, &seeds()),
+        Err(EcvrfError::InvalidProof)
+    );
+
+    // tamper with s
+    let mut proof = PROOF;
+    proof[80] ^= 1;
+    assert_eq!(
+        ecvrf::verify(&PUBLIC_KEY, &proof, &seeds()),
+        Err(EcvrfError::InvalidProof)
+    );
+
+    // s greater than the curve order
+    let mut proof = PROOF;
+    proof[49..].copy_from_slice(&[0xFF; 32]);
+    assert_eq!(
+        ecvrf::verify(&PUBLIC_KEY, &proof, &seeds()),
+        Err(EcvrfError::InvalidProofEncoding)
+    );
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+use vrf_sdk::{
+    error::{VRF_ERROR_CODE_END, VRF_ERROR_CODE_OFFSET},
+    VrfError,
+};
+
+/// The errors in the anchor IDL format, as published in `idl/errors.json`
+fn idl_errors() -> String {
+    let entries = VrfError::ALL
+        .iter()
+        .map(|err| {
+            format!(
+                "  {{\n    \"code\": {},\n    \"name\": \"{}\",\n    \"msg\": \"{}\"\n  }}",
+                u32::from(*err),
+                err.name(),
+                err
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+#[test]
+fn test_error_codes_are_stable() {
+    assert_eq!(u32::from(VrfError::NotFulfilled), 7777);
+    assert_eq!(u32::from(VrfError::InvalidProof), 7778);
+    assert_eq!(u32::from(VrfError::OracleDisabled), 7779);
+    assert_eq!(u32::from(VrfError::InvalidRange), 7780);
+    assert_eq!(u32::from(VrfError::InvalidCallback), 7787);
+    assert_eq!(u32::from(VrfError::DuplicateCallbackAccount), 7791);
+
+    for (i, err) in VrfError::ALL.iter().enumerate() {
+        let code = u32::from(*err);
+        assert_eq!(code, VRF_ERROR_CODE_OFFSET + i as u32);
+        assert!(code < VRF_ERROR_CODE_END);
+        assert_eq!(VrfError::from_code(code), Some(*err));
+    }
+    assert_eq!(VrfError::from_code(6000), None);
+    assert_eq!(VrfError::from_code(VRF_ERROR_CODE_END), None);
+}
+
+#[test]
+fn test_anchor_error() {
+    match Error::from(VrfError::FeeNotPaid) {
+        Error::AnchorError(e) => {
+            assert_eq!(e.error_code_number, 7786);
+            assert_eq!(e.error_name, "FeeNotPaid");
+            assert_eq!(e.error_msg, "The vrf oracle fee is not paid");
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[test]
+fn test_idl_errors_up_to_date() {
+    assert_eq!(include_str!("../idl/errors.json"), idl_errors());
+}
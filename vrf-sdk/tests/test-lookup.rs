@@ -0,0 +1,130 @@
+use anchor_lang::{__private::bytemuck::Zeroable, prelude::*};
+use vrf_sdk::{
+    vrf::{
+        lookup_table_addresses, validate_callback_lookup, CallbackLookup, VrfAccountData,
+        MAX_CALLBACK_LOOKUP_ACCOUNTS,
+    },
+    VrfError,
+};
+
+mod common;
+use common::vrf_error;
+
+/// Serialize an address lookup table account the way the runtime does
+fn table_data(addresses: &[Pubkey]) -> Vec<u8> {
+    let mut data = vec![0u8; 56];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    for address in addresses {
+        data.extend_from_slice(address.as_ref());
+    }
+    data
+}
+
+fn lookup(writable_indexes: Vec<u8>, readonly_indexes: Vec<u8>) -> CallbackLookup {
+    CallbackLookup {
+        table: Pubkey::new_unique(),
+        writable_indexes,
+        readonly_indexes,
+    }
+}
+
+#[test]
+fn test_callback_account_metas() {
+    let addresses = (0..8).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.callback.accounts_len = 1;
+    vrf.callback.accounts[0].pubkey = addresses[7];
+    vrf.callback.accounts[0].is_signer = true;
+
+    // without lookup table, only the static accounts
+    let metas = vrf.callback_account_metas(None).unwrap();
+    assert_eq!(metas.len(), 1);
+    assert!(metas[0].is_signer);
+
+    vrf.set_callback_lookup(&lookup(vec![3, 1], vec![0]))
+        .unwrap();
+    assert!(vrf.lookup.is_used());
+    assert_eq!(vrf.lookup.writable_indexes(), &[3, 1]);
+    assert_eq!(vrf.lookup.readonly_indexes(), &[0]);
+
+    let metas = vrf
+        .callback_account_metas(Some(&table_data(&addresses)))
+        .unwrap();
+    let expected = [
+        (addresses[7], true, false),
+        (addresses[3], false, true),
+        (addresses[1], false, true),
+        (addresses[0], false, false),
+    ];
+    assert_eq!(metas.len(), expected.len());
+    for (meta, (pubkey, is_signer, is_writable)) in metas.iter().zip(expected) {
+        assert_eq!(meta.pubkey, pubkey);
+        assert_eq!(meta.is_signer, is_signer);
+        assert_eq!(meta.is_writable, is_writable);
+    }
+
+    // the table is required once used
+    assert_eq!(
+        vrf_error(vrf.callback_account_metas(None)),
+        VrfError::InvalidLookupTable
+    );
+    // index out of the table
+    assert_eq!(
+        vrf_error(vrf.callback_account_metas(Some(&table_data(&addresses[..3])))),
+        VrfError::InvalidLookupTable
+    );
+}
+
+#[test]
+fn test_lookup_table_addresses() {
+    let addresses = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    assert_eq!(
+        lookup_table_addresses(&table_data(&addresses)).unwrap(),
+        addresses
+    );
+    assert!(lookup_table_addresses(&table_data(&[])).unwrap().is_empty());
+
+    // uninitialized
+    let mut data = table_data(&addresses);
+    data[0] = 0;
+    assert_eq!(
+        vrf_error(lookup_table_addresses(&data)),
+        VrfError::InvalidLookupTable
+    );
+    // truncated
+    let data = table_data(&addresses);
+    assert_eq!(
+        vrf_error(lookup_table_addresses(&data[..data.len() - 1])),
+        VrfError::InvalidLookupTable
+    );
+    assert_eq!(
+        vrf_error(lookup_table_addresses(&data[..40])),
+        VrfError::InvalidLookupTable
+    );
+}
+
+#[test]
+fn test_validate_callback_lookup() {
+    assert!(validate_callback_lookup(&lookup(vec![], vec![])).is_ok());
+    assert!(validate_callback_lookup(&lookup(
+        (0..MAX_CALLBACK_LOOKUP_ACCOUNTS as u8 / 2).collect(),
+        (MAX_CALLBACK_LOOKUP_ACCOUNTS as u8 / 2..MAX_CALLBACK_LOOKUP_ACCOUNTS as u8).collect(),
+    ))
+    .is_ok());
+
+    assert_eq!(
+        vrf_error(validate_callback_lookup(&CallbackLookup::default())),
+        VrfError::InvalidLookupTable
+    );
+    assert_eq!(
+        vrf_error(validate_callback_lookup(&lookup(
+            (0..=MAX_CALLBACK_LOOKUP_ACCOUNTS as u8).collect(),
+            vec![],
+        ))),
+        VrfError::TooManyCallbackAccounts
+    );
+    assert_eq!(
+        vrf_error(validate_callback_lookup(&lookup(vec![1, 2], vec![2]))),
+        VrfError::DuplicateCallbackAccount
+    );
+}
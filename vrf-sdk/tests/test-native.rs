@@ -0,0 +1,177 @@
+#![cfg(feature = "native")]
+
+use anchor_lang::{
+    error::ErrorCode,
+    prelude::*,
+    solana_program::instruction::{AccountMeta, Instruction},
+};
+use vrf_sdk::{
+    coordinator,
+    ecvrf::{VrfPublicKey, PUBLIC_KEY_BYTE_LEN},
+    native,
+    oracle::{Oracle, OracleStatus},
+    vrf::{VrfAccountView, PROOF_BYTE_LEN, VRF_RESULT_DISCRIMINATOR},
+    VrfError,
+};
+
+mod common;
+use common::custom_code;
+
+/// An active oracle account, asking for no fee.
+fn oracle_data() -> Vec<u8> {
+    let oracle = Oracle {
+        authority: Pubkey::new_unique(),
+        vrf_public_key: VrfPublicKey([2u8; PUBLIC_KEY_BYTE_LEN]),
+        status: OracleStatus::Active,
+        fee: 0,
+        fee_mint: Pubkey::default(),
+        fee_vault: Pubkey::new_unique(),
+    };
+    let mut data = vec![];
+    oracle.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_request_offsets_as_given() {
+    common::stub_clock();
+    let program_id = Pubkey::new_unique();
+    let (vrf_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut vrf_lamports, mut oracle_lamports) = (0, 0);
+    let mut vrf_data = vec![0u8; vrf_sdk::ACCOUNT_SIZE];
+    let mut oracle_data = oracle_data();
+    let vrf = AccountInfo::new(
+        &vrf_key,
+        false,
+        true,
+        &mut vrf_lamports,
+        &mut vrf_data,
+        &program_id,
+        false,
+        0,
+    );
+    let oracle = AccountInfo::new(
+        &oracle_key,
+        false,
+        false,
+        &mut oracle_lamports,
+        &mut oracle_data,
+        &coordinator::ID,
+        false,
+        0,
+    );
+
+    // the result first, and the bytes of a `VrfResult` placeholder after the proof
+    let data = [
+        &[0u8; 32][..],
+        &[0u8; PROOF_BYTE_LEN][..],
+        &VRF_RESULT_DISCRIMINATOR[..],
+    ]
+    .concat();
+    let callback = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(vrf_key, false)],
+        data: data.clone(),
+    };
+
+    // the proof overlaps the result
+    assert_eq!(
+        custom_code(native::request_randomness(
+            &[1],
+            &vrf,
+            &oracle,
+            None,
+            &callback,
+            0,
+            Some(16)
+        )),
+        u32::from(VrfError::InvalidCallbackOffset)
+    );
+
+    native::request_randomness(&[1], &vrf, &oracle, None, &callback, 0, Some(32)).unwrap();
+
+    let vrf_data = vrf.try_borrow_data().unwrap();
+    let view = VrfAccountView::from_account_data(&vrf_data).unwrap();
+    let callback = view.callback();
+    assert_eq!(callback.result_offset(), Some(0));
+    assert_eq!(callback.proof_offset(), Some(32));
+    assert_eq!(
+        callback
+            .fill_ix_data(&[5u8; 32], &[7u8; PROOF_BYTE_LEN])
+            .unwrap(),
+        [
+            &[5u8; 32][..],
+            &[7u8; PROOF_BYTE_LEN][..],
+            &VRF_RESULT_DISCRIMINATOR[..],
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn test_request_checks() {
+    let program_id = Pubkey::new_unique();
+    let (vrf_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut vrf_lamports, mut oracle_lamports) = (0, 0);
+    let mut vrf_data = vec![0u8; vrf_sdk::ACCOUNT_SIZE];
+    let mut oracle_data = vec![];
+    let other_program = Pubkey::new_unique();
+    let vrf = AccountInfo::new(
+        &vrf_key,
+        false,
+        true,
+        &mut vrf_lamports,
+        &mut vrf_data,
+        &other_program,
+        false,
+        0,
+    );
+    let oracle = AccountInfo::new(
+        &oracle_key,
+        false,
+        false,
+        &mut oracle_lamports,
+        &mut oracle_data,
+        &program_id,
+        false,
+        0,
+    );
+
+    let callback = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(vrf_key, false)],
+        data: vec![0u8; 33],
+    };
+
+    // the result does not fit
+    assert_eq!(
+        custom_code(native::request_randomness(
+            &[1],
+            &vrf,
+            &oracle,
+            None,
+            &callback,
+            2,
+            None
+        )),
+        u32::from(VrfError::InvalidCallbackOffset)
+    );
+    // the callback is not the program owning the vrf account
+    assert_eq!(
+        custom_code(native::request_randomness(
+            &[1],
+            &vrf,
+            &oracle,
+            None,
+            &callback,
+            1,
+            None
+        )),
+        ErrorCode::AccountOwnedByWrongProgram as u32
+    );
+    // not a vrf account yet
+    assert_eq!(
+        custom_code(native::cancel_request(&other_program, &vrf, 0)),
+        ErrorCode::AccountDiscriminatorMismatch as u32
+    );
+}
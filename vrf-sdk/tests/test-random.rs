@@ -0,0 +1,190 @@
+use solana_program::hash::hashv;
+use vrf_sdk::{vrf::VRF_RESULT_DISCRIMINATOR, VrfError, VrfResult};
+
+/// Deterministic, well distributed results
+fn results(count: u32) -> impl Iterator<Item = VrfResult> {
+    (0..count).map(|i| VrfResult {
+        result: hashv(&[b"test-random", &i.to_le_bytes()]).to_bytes(),
+    })
+}
+
+fn result_from_halves(first: u128, second: u128) -> VrfResult {
+    let mut result = [0u8; 32];
+    result[..16].copy_from_slice(&first.to_be_bytes());
+    result[16..].copy_from_slice(&second.to_be_bytes());
+    VrfResult { result }
+}
+
+#[test]
+fn test_small_ranges_exhaustive() {
+    for start in -8i32..=8 {
+        for end in start..=8 {
+            let mut seen = vec![false; (end - start + 1) as usize];
+
+            for vrf_result in results(512) {
+                let value = vrf_result.random(start..=end).unwrap();
+                assert!(
+                    start <= value && value <= end,
+                    "{value} not in {start}..={end}"
+                );
+                seen[(value - start) as usize] = true;
+            }
+
+            assert!(
+                seen.iter().all(|seen| *seen),
+                "{start}..={end} never produced some values: {seen:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_single_value_range() {
+    for vrf_result in results(16) {
+        assert_eq!(vrf_result.random(0..=0).unwrap(), 0);
+        assert_eq!(vrf_result.random(-3i8..=-3).unwrap(), -3);
+        assert_eq!(vrf_result.random(u64::MAX..=u64::MAX).unwrap(), u64::MAX);
+    }
+}
+
+#[test]
+fn test_full_ranges() {
+    let mut seen_u8 = [false; 256];
+    let mut seen_i8 = [false; 256];
+    for vrf_result in results(4096) {
+        seen_u8[vrf_result.random(u8::MIN..=u8::MAX).unwrap() as usize] = true;
+        seen_i8[(vrf_result.random(i8::MIN..=i8::MAX).unwrap() as i16 + 128) as usize] = true;
+
+        // must not panic nor overflow
+        vrf_result.random(i128::MIN..=i128::MAX).unwrap();
+        vrf_result.random(u128::MIN..=u128::MAX).unwrap();
+        vrf_result.random(i64::MIN..=i64::MAX).unwrap();
+    }
+
+    assert!(seen_u8.iter().all(|seen| *seen));
+    assert!(seen_i8.iter().all(|seen| *seen));
+}
+
+#[test]
+fn test_uniform_distribution() {
+    const SAMPLES: u32 = 60_000;
+    let mut counts = [0u32; 6];
+    for vrf_result in results(SAMPLES) {
+        counts[vrf_result.random(1u8..=6).unwrap() as usize - 1] += 1;
+    }
+
+    // expected 10_000 each, allow ~5 standard deviations
+    for count in counts {
+        assert!((9_550..=10_450).contains(&count), "{counts:?}");
+    }
+}
+
+#[test]
+fn test_rejection_sampling() {
+    // 2^128 % 3 == 1, so u128::MAX is the only rejected candidate for a range of 3 values
+    let vrf_result = result_from_halves(u128::MAX, 5);
+    assert_eq!(vrf_result.random(0..=2).unwrap(), 5 % 3);
+
+    // u128::MAX - 1 is accepted
+    let vrf_result = result_from_halves(u128::MAX - 1, 5);
+    assert_eq!(vrf_result.random(0..=2).unwrap(), (u128::MAX - 1) % 3);
+
+    // both halves rejected, the next candidate comes from sha256(result)
+    let vrf_result = result_from_halves(u128::MAX, u128::MAX);
+    let rehashed = hashv(&[&vrf_result.result]).to_bytes();
+    let expected = u128::from_be_bytes(rehashed[..16].try_into().unwrap()) % 3;
+    assert_eq!(vrf_result.random(10..=12).unwrap(), 10 + expected as i32);
+}
+
+#[test]
+fn test_deterministic() {
+    for vrf_result in results(64) {
+        assert_eq!(
+            vrf_result.random(-1000..=1000).unwrap(),
+            vrf_result.random(-1000..=1000).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_errors() {
+    let vrf_result = results(1).next().unwrap();
+    #[allow(clippy::reversed_empty_ranges)]
+    let range = 5..=4;
+    assert_eq!(vrf_result.random(range), Err(VrfError::InvalidRange));
+
+    let empty = VrfResult { result: [0u8; 32] };
+    assert_eq!(empty.random(0..=1), Err(VrfError::NotFulfilled));
+
+    let default = VrfResult::default();
+    assert_eq!(default.result, VRF_RESULT_DISCRIMINATOR);
+    assert_eq!(default.random(0..=1), Err(VrfError::NotFulfilled));
+}
+
+#[test]
+fn test_stream_matches_nth_random() {
+    for vrf_result in results(32) {
+        let mut stream = vrf_result.stream().unwrap();
+        for index in 0..64 {
+            assert_eq!(stream.index(), index);
+            assert_eq!(
+                stream.next_random(0u32..=999).unwrap(),
+                vrf_result.nth_random(index, 0u32..=999).unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_nth_random_ranges() {
+    let vrf_result = results(1).next().unwrap();
+    for index in 0..2048 {
+        let value = vrf_result.nth_random(index, -3i64..=3).unwrap();
+        assert!((-3..=3).contains(&value));
+    }
+    assert!(vrf_result.nth_random(u64::MAX, u8::MIN..=u8::MAX).is_ok());
+}
+
+#[test]
+fn test_stream_independent_values() {
+    // consecutive values of a stream must be independent:
+    // every pair of dices (a, b) should be as likely as the others
+    const SAMPLES: u32 = 36_000;
+    let mut counts = [[0u32; 6]; 6];
+    for vrf_result in results(SAMPLES / 2) {
+        let mut stream = vrf_result.stream().unwrap();
+        for _ in 0..2 {
+            let a = stream.next_random(0usize..=5).unwrap();
+            let b = stream.next_random(0usize..=5).unwrap();
+            counts[a][b] += 1;
+        }
+    }
+
+    // expected 1_000 each, allow ~5 standard deviations
+    for count in counts.iter().flatten() {
+        assert!((840..=1_160).contains(count), "{counts:?}");
+    }
+
+    // the stream is not a copy of `random`
+    let same = results(1024)
+        .filter(|vrf_result| {
+            vrf_result.random(0..=u32::MAX).unwrap()
+                == vrf_result.nth_random(0, 0..=u32::MAX).unwrap()
+        })
+        .count();
+    assert!(same <= 1);
+}
+
+#[test]
+fn test_stream_errors() {
+    let empty = VrfResult::default();
+    assert!(matches!(empty.stream(), Err(VrfError::NotFulfilled)));
+    assert_eq!(empty.nth_random(3, 0..=1), Err(VrfError::NotFulfilled));
+
+    let mut stream = results(1).next().unwrap().stream().unwrap();
+    #[allow(clippy::reversed_empty_ranges)]
+    let range = 1..=0;
+    assert_eq!(stream.next_random(range), Err(VrfError::InvalidRange));
+    // a failed draw does not consume an index
+    assert_eq!(stream.index(), 0);
+}
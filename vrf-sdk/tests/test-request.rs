@@ -0,0 +1,69 @@
+use anchor_lang::{prelude::*, solana_program::instruction::AccountMeta};
+use vrf_sdk::{
+    vrf::{validate_request, MAX_CALLBACK_ACCOUNTS, MAX_CALLBACK_IX_DATA_LEN, SEEDS_BYTE_LEN},
+    VrfError,
+};
+
+mod common;
+use common::vrf_error;
+
+fn accounts(count: usize) -> Vec<AccountMeta> {
+    (0..count)
+        .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+        .collect()
+}
+
+#[test]
+fn test_valid_request() {
+    assert!(validate_request(&[1], &[], &[]).is_ok());
+    assert!(validate_request(
+        &[7u8; SEEDS_BYTE_LEN],
+        &accounts(MAX_CALLBACK_ACCOUNTS),
+        &[0u8; MAX_CALLBACK_IX_DATA_LEN],
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_invalid_seeds() {
+    let seeds_error = VrfError::InvalidSeeds;
+    assert_eq!(vrf_error(validate_request(&[], &[], &[])), seeds_error);
+    assert_eq!(
+        vrf_error(validate_request(&[0u8; 4], &[], &[])),
+        seeds_error
+    );
+    assert_eq!(
+        vrf_error(validate_request(&[1u8; SEEDS_BYTE_LEN + 1], &[], &[])),
+        VrfError::SeedsTooLong
+    );
+}
+
+#[test]
+fn test_callback_limits() {
+    assert_eq!(
+        vrf_error(validate_request(
+            &[1],
+            &accounts(MAX_CALLBACK_ACCOUNTS + 1),
+            &[]
+        )),
+        VrfError::TooManyCallbackAccounts
+    );
+    assert_eq!(
+        vrf_error(validate_request(
+            &[1],
+            &[],
+            &[0u8; MAX_CALLBACK_IX_DATA_LEN + 1]
+        )),
+        VrfError::CallbackDataTooLarge
+    );
+}
+
+#[test]
+fn test_duplicate_callback_account() {
+    let mut metas = accounts(3);
+    metas.push(AccountMeta::new_readonly(metas[1].pubkey, true));
+    assert_eq!(
+        vrf_error(validate_request(&[1], &metas, &[])),
+        VrfError::DuplicateCallbackAccount
+    );
+}
@@ -0,0 +1,66 @@
+use anchor_lang::{__private::bytemuck::Zeroable, prelude::*};
+use vrf_sdk::{
+    compact::VrfCompactAccountData,
+    vrf::{VrfAccountData, VrfStatus, LAYOUT_VERSION},
+    VrfError,
+};
+
+mod common;
+use common::{header, vrf_error};
+
+#[test]
+fn test_rearm_fulfilled() {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.round = 3;
+    vrf.header = header(VrfStatus::Fulfilled);
+    vrf.result.result = [1u8; 32];
+    vrf.proof = [2u8; 81];
+    vrf.seeds = [3u8; 32];
+    vrf.lookup.table = Pubkey::new_unique();
+    vrf.fee_paid = 5_000;
+
+    vrf.rearm().unwrap();
+
+    let last_round = vrf.last_round;
+    let (round, result, proof) = (last_round.round, last_round.result.result, last_round.proof);
+    assert_eq!(round, 3);
+    assert_eq!(result, [1u8; 32]);
+    assert_eq!(proof, [2u8; 81]);
+
+    // the new round starts from a clean request
+    assert!(!vrf.is_fulfilled());
+    assert_eq!(vrf.proof, [0u8; 81]);
+    assert_eq!(vrf.seeds, [0u8; 32]);
+    assert!(!vrf.lookup.is_used());
+    let fee_paid = vrf.fee_paid;
+    assert_eq!(fee_paid, 0);
+    // opened by `request_randomness`
+    let round = vrf.round;
+    assert_eq!(round, 3);
+}
+
+#[test]
+fn test_rearm_closed_only() {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.header = header(VrfStatus::Pending);
+    assert_eq!(vrf_error(vrf.rearm()), VrfError::InvalidStatus);
+
+    for status in [VrfStatus::Expired, VrfStatus::Cancelled] {
+        vrf.header = header(status);
+        vrf.rearm().unwrap();
+    }
+
+    let mut compact = VrfCompactAccountData::zeroed();
+    compact.layout_version = LAYOUT_VERSION;
+    compact.header = header(VrfStatus::Pending);
+    assert_eq!(vrf_error(compact.rearm()), VrfError::InvalidStatus);
+
+    compact.round = 1;
+    compact.header = header(VrfStatus::Fulfilled);
+    compact.result.result = [9u8; 32];
+    compact.rearm().unwrap();
+    let last_round = compact.last_round;
+    let (round, result) = (last_round.round, last_round.result.result);
+    assert_eq!(round, 1);
+    assert_eq!(result, [9u8; 32]);
+}
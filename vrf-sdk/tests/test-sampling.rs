@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use solana_program::hash::hashv;
+use vrf_sdk::{random::MAX_SAMPLE_LEN, VrfError, VrfResult};
+
+/// Deterministic, well distributed results
+fn results(count: u32) -> impl Iterator<Item = VrfResult> {
+    (0..count).map(|i| VrfResult {
+        result: hashv(&[b"test-sampling", &i.to_le_bytes()]).to_bytes(),
+    })
+}
+
+/// Assert `count` is within ~5 standard deviations of a binomial(samples, p)
+fn assert_binomial(count: u32, samples: u32, p: f64) {
+    let mean = samples as f64 * p;
+    let deviation = (samples as f64 * p * (1.0 - p)).sqrt();
+    assert!(
+        (count as f64 - mean).abs() <= 5.0 * deviation + 1.0,
+        "count={count}, expected={mean}"
+    );
+}
+
+#[test]
+fn test_shuffle_permutations() {
+    const SAMPLES: u32 = 24_000;
+    let mut counts = HashMap::<[u8; 4], u32>::new();
+    for vrf_result in results(SAMPLES) {
+        let mut items = [0u8, 1, 2, 3];
+        vrf_result.shuffle(&mut items).unwrap();
+        *counts.entry(items).or_default() += 1;
+    }
+
+    assert_eq!(counts.len(), 24);
+    for count in counts.values() {
+        assert_binomial(*count, SAMPLES, 1.0 / 24.0);
+    }
+}
+
+#[test]
+fn test_shuffle_keeps_items() {
+    for vrf_result in results(64) {
+        let mut items = (0..52).collect::<Vec<u32>>();
+        vrf_result.shuffle(&mut items).unwrap();
+
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..52).collect::<Vec<u32>>());
+    }
+
+    let vrf_result = results(1).next().unwrap();
+    vrf_result.shuffle::<u8>(&mut []).unwrap();
+    let mut single = [7];
+    vrf_result.shuffle(&mut single).unwrap();
+    assert_eq!(single, [7]);
+}
+
+#[test]
+fn test_choose_weighted_distribution() {
+    const SAMPLES: u32 = 20_000;
+    let weights = [1u64, 0, 2, 7, 0];
+    let mut counts = [0u32; 5];
+    for vrf_result in results(SAMPLES) {
+        counts[vrf_result.choose_weighted(&weights).unwrap()] += 1;
+    }
+
+    assert_eq!(counts[1], 0);
+    assert_eq!(counts[4], 0);
+    assert_binomial(counts[0], SAMPLES, 0.1);
+    assert_binomial(counts[2], SAMPLES, 0.2);
+    assert_binomial(counts[3], SAMPLES, 0.7);
+}
+
+#[test]
+fn test_choose_weighted_large_weights() {
+    let weights = [u64::MAX, u64::MAX, u64::MAX];
+    let mut counts = [0u32; 3];
+    for vrf_result in results(3_000) {
+        counts[vrf_result.choose_weighted(&weights).unwrap()] += 1;
+    }
+
+    for count in counts {
+        assert_binomial(count, 3_000, 1.0 / 3.0);
+    }
+}
+
+#[test]
+fn test_sample_without_replacement_distribution() {
+    const SAMPLES: u32 = 20_000;
+    let mut counts = HashMap::<(u64, u64), u32>::new();
+    for vrf_result in results(SAMPLES) {
+        let sample = vrf_result.sample_without_replacement(5, 2).unwrap();
+        assert_eq!(sample.len(), 2);
+        assert_ne!(sample[0], sample[1]);
+        *counts.entry((sample[0], sample[1])).or_default() += 1;
+    }
+
+    // every ordered pair of distinct values
+    assert_eq!(counts.len(), 20);
+    for count in counts.values() {
+        assert_binomial(*count, SAMPLES, 1.0 / 20.0);
+    }
+}
+
+#[test]
+fn test_sample_without_replacement_bounds() {
+    for vrf_result in results(32) {
+        // the whole population is a permutation
+        let mut sample = vrf_result.sample_without_replacement(10, 10).unwrap();
+        sample.sort_unstable();
+        assert_eq!(sample, (0..10).collect::<Vec<u64>>());
+
+        // large population, the cost only depends on k
+        let sample = vrf_result.sample_without_replacement(u64::MAX, 16).unwrap();
+        let mut distinct = sample.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 16);
+
+        assert!(vrf_result
+            .sample_without_replacement(0, 0)
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[test]
+fn test_bernoulli_distribution() {
+    const SAMPLES: u32 = 20_000;
+    for p_bps in [1u16, 2_500, 5_000, 9_999] {
+        let count = results(SAMPLES)
+            .filter(|vrf_result| vrf_result.bernoulli(p_bps).unwrap())
+            .count();
+        assert_binomial(count as u32, SAMPLES, p_bps as f64 / 10_000.0);
+    }
+
+    for vrf_result in results(1_000) {
+        assert!(!vrf_result.bernoulli(0).unwrap());
+        assert!(vrf_result.bernoulli(10_000).unwrap());
+    }
+}
+
+#[test]
+fn test_stream_helpers_are_independent() {
+    // two consecutive coin flips of a stream must not be correlated
+    const SAMPLES: u32 = 20_000;
+    let mut both = 0;
+    for vrf_result in results(SAMPLES) {
+        let mut stream = vrf_result.stream().unwrap();
+        let first = stream.bernoulli(5_000).unwrap();
+        let second = stream.bernoulli(5_000).unwrap();
+        if first && second {
+            both += 1;
+        }
+        assert_eq!(stream.index(), 2);
+    }
+    assert_binomial(both, SAMPLES, 0.25);
+}
+
+#[test]
+fn test_sampling_errors() {
+    let vrf_result = results(1).next().unwrap();
+    assert_eq!(
+        vrf_result.choose_weighted(&[]),
+        Err(VrfError::InvalidWeights)
+    );
+    assert_eq!(
+        vrf_result.choose_weighted(&[0, 0]),
+        Err(VrfError::InvalidWeights)
+    );
+    assert_eq!(
+        vrf_result.bernoulli(10_001),
+        Err(VrfError::InvalidProbability)
+    );
+    assert_eq!(
+        vrf_result.sample_without_replacement(3, 4),
+        Err(VrfError::SampleTooLarge)
+    );
+
+    // bounded, whatever the population
+    assert_eq!(
+        vrf_result
+            .sample_without_replacement(u64::MAX, MAX_SAMPLE_LEN)
+            .unwrap()
+            .len(),
+        MAX_SAMPLE_LEN
+    );
+    assert_eq!(
+        vrf_result.sample_without_replacement(u64::MAX, usize::MAX),
+        Err(VrfError::TooManyItems)
+    );
+    let mut items = vec![0u8; MAX_SAMPLE_LEN + 1];
+    assert_eq!(vrf_result.shuffle(&mut items), Err(VrfError::TooManyItems));
+    vrf_result.shuffle(&mut items[1..]).unwrap();
+
+    let empty = VrfResult::default();
+    assert_eq!(empty.shuffle(&mut [1, 2]), Err(VrfError::NotFulfilled));
+    assert_eq!(empty.bernoulli(1), Err(VrfError::NotFulfilled));
+}
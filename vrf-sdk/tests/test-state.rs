@@ -0,0 +1,116 @@
+use std::mem::size_of;
+
+use anchor_lang::{
+    __private::bytemuck, prelude::*, solana_program::instruction::AccountMeta, AccountDeserialize,
+    AccountSerialize, Discriminator,
+};
+use vrf_sdk::{
+    compact::{account_size, VrfCompactAccountData},
+    vrf::{VrfAccountData, VrfAccountView, LAYOUT_VERSION},
+    vrf_state, VrfProof, VrfResult, ACCOUNT_SIZE,
+};
+
+declare_id!("3gfec8ANuaWzkNhAR5QRjUvGqUjMYLJ3YnSVhgMkugqv");
+
+mod other_program {
+    anchor_lang::declare_id!("6trpiXViFkrXFR1F1nMDGMyigUo89c53La2Bpc4mMwyG");
+}
+
+/// A bet settled by the VRF result
+#[vrf_state]
+pub struct BetState {
+    pub bettor: Pubkey,
+    pub bet_amount: u64,
+}
+
+#[vrf_state(owner = other_program::ID, compact)]
+pub struct CompactBetState {
+    pub bettor: Pubkey,
+    pub bet_amount: u64,
+}
+
+#[test]
+fn test_state_fields() {
+    assert_eq!(<BetState as Owner>::owner(), crate::ID);
+    assert_eq!(<CompactBetState as Owner>::owner(), other_program::ID);
+    assert_eq!(
+        <CompactBetState as Discriminator>::DISCRIMINATOR,
+        VrfAccountData::DISCRIMINATOR
+    );
+    assert_eq!(size_of::<BetState>(), size_of::<VrfAccountData>() + 40);
+    assert_eq!(
+        size_of::<CompactBetState>(),
+        size_of::<VrfCompactAccountData>() + 40
+    );
+}
+
+#[test]
+fn test_serialize_round_trip() {
+    let mut state: BetState = bytemuck::Zeroable::zeroed();
+    let bettor = Pubkey::new_unique();
+    state.bettor = bettor;
+    state.bet_amount = 1_000;
+    state.seeds = [3u8; 32];
+
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), 8 + size_of::<BetState>());
+    assert_eq!(&data[0..8], &VrfAccountData::DISCRIMINATOR);
+
+    let state = BetState::try_deserialize(&mut &data[..]).unwrap();
+    let (state_bettor, bet_amount, seeds) = (state.bettor, state.bet_amount, state.seeds);
+    assert_eq!(state_bettor, bettor);
+    assert_eq!(bet_amount, 1_000);
+    assert_eq!(seeds, [3u8; 32]);
+
+    // the VRF-server still reads a full account
+    assert!(data.len() > ACCOUNT_SIZE);
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert!(matches!(view, VrfAccountView::Full(..)));
+    assert_eq!(view.seeds(), [3u8; 32]);
+
+    // truncated
+    assert!(BetState::try_deserialize(&mut &data[..data.len() - 1]).is_err());
+}
+
+#[test]
+fn test_compact_state_fields() {
+    let program_id = other_program::ID;
+    let accounts = [
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+    ];
+    let mut ix_data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    (VrfResult::default(), VrfProof::default())
+        .serialize(&mut ix_data)
+        .unwrap();
+
+    let fields_len = size_of::<CompactBetState>() - size_of::<VrfCompactAccountData>();
+    let mut data = vec![0u8; account_size(accounts.len(), ix_data.len()) + fields_len];
+    data[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+    let bettor = Pubkey::new_unique();
+    {
+        let (state, callback_data) = data[8..].split_at_mut(size_of::<CompactBetState>());
+        let state = bytemuck::from_bytes_mut::<CompactBetState>(state);
+        state.bettor = bettor;
+        state.bet_amount = 42;
+        // as `init_request` does, without the clock
+        state.layout_version = LAYOUT_VERSION;
+        state
+            .set_callback(callback_data, program_id, &accounts, &ix_data)
+            .unwrap();
+        state.callback.fields_len = fields_len as u16;
+    }
+
+    // the callback is read past the program fields
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert!(matches!(view, VrfAccountView::Compact(..)));
+    assert_eq!(view.callback().program_id, program_id);
+    assert_eq!(view.callback().ix_data, &ix_data[..]);
+    assert_eq!(view.callback_account_metas(None).unwrap(), accounts);
+
+    let state = CompactBetState::try_deserialize(&mut &data[..]).unwrap();
+    let (state_bettor, bet_amount) = (state.bettor, state.bet_amount);
+    assert_eq!(state_bettor, bettor);
+    assert_eq!(bet_amount, 42);
+}
@@ -0,0 +1,115 @@
+use anchor_lang::{
+    __private::bytemuck::{self, Zeroable},
+    prelude::*,
+};
+use vrf_sdk::vrf::{VrfAccountData, VrfAccountView, VrfStatus};
+
+mod common;
+use common::{baseline_account, header};
+
+#[test]
+fn test_status_without_header() {
+    let mut vrf = VrfAccountData::zeroed();
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+    assert_eq!(vrf.request_slot(), None);
+
+    vrf.result.result = [1u8; 32];
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(vrf.fulfilled_at(), None);
+}
+
+#[test]
+fn test_fulfill_without_header() {
+    common::stub_clock();
+
+    // a request made before the status header, fulfilled as usual
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.result.result = [1u8; 32];
+    vrf.set_fulfilled().unwrap();
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(
+        vrf.fulfilled_at(),
+        Some((common::CLOCK_TIMESTAMP, common::CLOCK_SLOT))
+    );
+    assert!(vrf.set_fulfilled().is_err());
+
+    // fulfilled before the upgrade, it cannot be cancelled
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.result.result = [1u8; 32];
+    assert!(vrf.cancel(0).is_err());
+}
+
+#[test]
+fn test_fulfill_baseline_account() {
+    common::stub_clock();
+    let program_id = Pubkey::new_unique();
+    let accounts = [AccountMeta::new(Pubkey::new_unique(), false)];
+
+    // an in-flight request written by the sdk before the status header, not migrated
+    let mut data = baseline_account([3u8; 32], program_id, &accounts);
+    let vrf = bytemuck::from_bytes_mut::<VrfAccountData>(&mut data[8..]);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+    let filled = vrf
+        .callback
+        .fill_ix_data(&[5u8; 32], &[0u8; vrf_sdk::vrf::PROOF_BYTE_LEN])
+        .unwrap();
+    assert_eq!(&filled[16..48], &[5u8; 32]);
+
+    vrf.result.result = [5u8; 32];
+    vrf.set_fulfilled().unwrap();
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(vrf.seeds, [3u8; 32]);
+    assert!(vrf.set_fulfilled().is_err());
+
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert_eq!(view.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(view.callback().program_id, program_id);
+
+    // fulfilled before the status header, it cannot be cancelled
+    let mut data = baseline_account([3u8; 32], program_id, &accounts);
+    data[8..40].copy_from_slice(&[5u8; 32]);
+    let vrf = bytemuck::from_bytes_mut::<VrfAccountData>(&mut data[8..]);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert!(vrf.cancel(0).is_err());
+}
+
+#[test]
+fn test_status_header() {
+    let mut vrf = VrfAccountData::zeroed();
+
+    vrf.header = header(VrfStatus::Pending);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+    assert_eq!(vrf.request_slot(), Some(10));
+    assert_eq!(vrf.fulfilled_at(), None);
+
+    vrf.header = header(VrfStatus::Fulfilled);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(vrf.fulfilled_at(), Some((1_700_000_000, 42)));
+
+    for status in [VrfStatus::Expired, VrfStatus::Cancelled] {
+        vrf.header = header(status);
+        assert_eq!(vrf.status().unwrap(), status);
+        assert_eq!(vrf.fulfilled_at(), None);
+    }
+
+    vrf.header.status = 0;
+    assert!(vrf.status().is_err());
+}
+
+#[test]
+fn test_account_size_unchanged() {
+    assert_eq!(vrf_sdk::ACCOUNT_SIZE, 8 + 3328);
+}
+
+#[test]
+fn test_is_expired() {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.request_timestamp = 1_000;
+
+    assert!(!vrf.is_expired(600, 1_000));
+    assert!(!vrf.is_expired(600, 1_599));
+    assert!(vrf.is_expired(600, 1_600));
+    assert!(vrf.is_expired(0, 1_000));
+    // must not overflow
+    assert!(!vrf.is_expired(i64::MAX, i64::MAX - 1));
+}
@@ -0,0 +1,203 @@
+use anchor_lang::{prelude::*, Discriminator, __private::bytemuck, __private::bytemuck::Zeroable};
+use vrf_sdk::{
+    compact::{VrfCompactAccountData, HEADER_SIZE},
+    vrf::{
+        VrfAccountData, VrfAccountView, VrfStatus, LAYOUT_VERSION, OLDEST_COMPACT_LAYOUT_VERSION,
+        PROOF_BYTE_LEN, STATUS_HEADER_VERSION,
+    },
+    VrfError, ACCOUNT_SIZE,
+};
+
+mod common;
+use common::{baseline_account, vrf_error, BaselineVrfAccountData};
+
+/// The account data of a full VRF account
+fn account_data(vrf: &VrfAccountData) -> Vec<u8> {
+    let mut data = VrfAccountData::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(vrf));
+    data
+}
+
+#[test]
+fn test_baseline_layout() {
+    // the fields of the accounts created before versioning are read in place
+    let baseline = BaselineVrfAccountData::zeroed();
+    let vrf = VrfAccountData::zeroed();
+    assert_eq!(
+        std::mem::size_of::<BaselineVrfAccountData>(),
+        std::mem::size_of::<VrfAccountData>()
+    );
+    assert_eq!(offset_of!(baseline, result), offset_of!(vrf, result));
+    assert_eq!(offset_of!(baseline, proof), offset_of!(vrf, legacy_proof));
+    assert_eq!(offset_of!(baseline, seeds), offset_of!(vrf, seeds));
+    assert_eq!(
+        offset_of!(baseline, request_timestamp),
+        offset_of!(vrf, request_timestamp)
+    );
+    assert_eq!(
+        offset_of!(baseline, callback.program_id),
+        offset_of!(vrf, callback.program_id)
+    );
+    assert_eq!(
+        offset_of!(baseline, callback.accounts),
+        offset_of!(vrf, callback.accounts)
+    );
+    assert_eq!(
+        offset_of!(baseline, callback.accounts_len),
+        offset_of!(vrf, callback.accounts_len)
+    );
+    assert_eq!(
+        offset_of!(baseline, callback.ix_data),
+        offset_of!(vrf, callback.ix_data)
+    );
+    assert_eq!(
+        offset_of!(baseline, callback.ix_data_len),
+        offset_of!(vrf, callback.ix_data_len)
+    );
+    // every newer field is carved from the reserved area
+    assert_eq!(offset_of!(baseline, _buf), offset_of!(vrf, requester));
+}
+
+#[test]
+fn test_migrate_legacy_account() {
+    // an in-flight request made before the layout was versioned
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.seeds = [7u8; 32];
+    assert_eq!(vrf.layout_version().unwrap(), 0);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+
+    assert_eq!(vrf.migrate().unwrap(), 0);
+    assert_eq!(vrf.layout_version().unwrap(), LAYOUT_VERSION);
+    assert_eq!(vrf.header.version, STATUS_HEADER_VERSION);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+    assert_eq!(vrf.request_slot(), None);
+    assert_eq!(vrf.seeds, [7u8; 32]);
+
+    // already up to date
+    assert_eq!(vrf.migrate().unwrap(), LAYOUT_VERSION);
+
+    // and still fulfilled as usual
+    common::stub_clock();
+    vrf.result.result = [1u8; 32];
+    vrf.set_fulfilled().unwrap();
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    assert_eq!(
+        vrf.fulfilled_at(),
+        Some((common::CLOCK_TIMESTAMP, common::CLOCK_SLOT))
+    );
+}
+
+#[test]
+fn test_migrate_baseline_account() {
+    // an in-flight request written by the sdk before versioning
+    let program_id = Pubkey::new_unique();
+    let accounts = [
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+    ];
+    let mut data = baseline_account([7u8; 32], program_id, &accounts);
+
+    // as read by the VRF-server
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert_eq!(view.layout_version().unwrap(), 0);
+    assert_eq!(view.status().unwrap(), VrfStatus::Pending);
+    assert_eq!(view.seeds(), [7u8; 32]);
+    let callback = view.callback();
+    assert_eq!(callback.program_id, program_id);
+    assert_eq!(callback.accounts(), accounts);
+    assert_eq!(callback.result_offset(), Some(16));
+    assert_eq!(callback.proof_offset(), None);
+
+    let vrf = bytemuck::from_bytes_mut::<VrfAccountData>(&mut data[8..]);
+    assert_eq!(vrf.migrate().unwrap(), 0);
+    assert_eq!(vrf.layout_version().unwrap(), LAYOUT_VERSION);
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Pending);
+    assert_eq!(vrf.seeds, [7u8; 32]);
+    let request_timestamp = vrf.request_timestamp;
+    assert_eq!(request_timestamp, 1_600_000_000);
+    assert_eq!(vrf.legacy_proof, [9u8; 80]);
+    assert_eq!(vrf.proof, [0u8; PROOF_BYTE_LEN]);
+    assert_eq!(vrf.callback.program_id, program_id);
+    assert_eq!(vrf.callback.result_offset(), Some(16));
+    assert_eq!({ vrf.round }, 0);
+    assert_eq!(vrf.oracle, Pubkey::default());
+}
+
+#[test]
+fn test_migrate_fulfilled_legacy_account() {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.result.result = [1u8; 32];
+
+    vrf.migrate().unwrap();
+    assert_eq!(vrf.status().unwrap(), VrfStatus::Fulfilled);
+    // fulfilled before the status header, when is unknown
+    assert_eq!(vrf.fulfilled_at(), None);
+    assert!(vrf.set_fulfilled().is_err());
+}
+
+#[test]
+fn test_newer_layout() {
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.layout_version = LAYOUT_VERSION + 1;
+
+    assert_eq!(
+        vrf_error(vrf.layout_version()),
+        VrfError::UnsupportedLayoutVersion
+    );
+    assert_eq!(vrf_error(vrf.status()), VrfError::UnsupportedLayoutVersion);
+    assert_eq!(vrf_error(vrf.migrate()), VrfError::UnsupportedLayoutVersion);
+    assert_eq!(
+        vrf_error(VrfAccountView::from_account_data(&account_data(&vrf))),
+        VrfError::UnsupportedLayoutVersion
+    );
+
+    let mut compact = vec![0u8; HEADER_SIZE];
+    compact[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+    let (vrf, _) = VrfCompactAccountData::split_mut(&mut compact[8..]).unwrap();
+    vrf.layout_version = LAYOUT_VERSION + 1;
+    assert_eq!(
+        vrf_error(VrfAccountView::from_account_data(&compact)),
+        VrfError::UnsupportedLayoutVersion
+    );
+}
+
+#[test]
+fn test_outdated_compact_layout() {
+    // the compact header shifted before its layout version 2
+    let mut compact = vec![0u8; HEADER_SIZE];
+    compact[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+    for version in 0..OLDEST_COMPACT_LAYOUT_VERSION {
+        let (vrf, _) = VrfCompactAccountData::split_mut(&mut compact[8..]).unwrap();
+        vrf.layout_version = version;
+        assert_eq!(vrf_error(vrf.status()), VrfError::OutdatedLayoutVersion);
+        assert_eq!(vrf_error(vrf.migrate()), VrfError::OutdatedLayoutVersion);
+        assert_eq!(
+            vrf_error(VrfAccountView::from_account_data(&compact)),
+            VrfError::OutdatedLayoutVersion
+        );
+    }
+
+    let (vrf, _) = VrfCompactAccountData::split_mut(&mut compact[8..]).unwrap();
+    vrf.layout_version = LAYOUT_VERSION;
+    assert!(VrfAccountView::from_account_data(&compact).is_ok());
+
+    // the full layout never shifted
+    let mut vrf = VrfAccountData::zeroed();
+    vrf.layout_version = 1;
+    assert_eq!(vrf.migrate().unwrap(), 1);
+    assert_eq!(vrf.layout_version().unwrap(), LAYOUT_VERSION);
+}
+
+#[test]
+fn test_view_layout_version() {
+    let mut vrf = VrfAccountData::zeroed();
+    let data = account_data(&vrf);
+    assert_eq!(data.len(), ACCOUNT_SIZE);
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert_eq!(view.layout_version().unwrap(), 0);
+
+    vrf.layout_version = LAYOUT_VERSION;
+    let data = account_data(&vrf);
+    let view = VrfAccountView::from_account_data(&data).unwrap();
+    assert_eq!(view.layout_version().unwrap(), LAYOUT_VERSION);
+}
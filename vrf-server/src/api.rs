@@ -0,0 +1,259 @@
+//! Public HTTP API, so anyone can check a result against its seeds and proof.
+//!
+//! - `GET /oracle`: our oracle account and VRF public key.
+//! - `GET /vrf/:vrf`: every request of a VRF account.
+//! - `GET /transaction/:signature`: the requests of a request or response transaction.
+//!
+//! Bytes are hex encoded, the proof and result only once the request is fulfilled.
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+use vrf_sdk::__private::Pubkey;
+
+use crate::{
+    config::VrfConfig,
+    ledger::{Ledger, LedgerEntry, LedgerStatus},
+};
+
+pub struct ApiState {
+    pub ledger: Arc<Ledger>,
+    pub oracle: Pubkey,
+    /// Hex encoded compressed public key.
+    pub vrf_public_key: String,
+}
+
+impl ApiState {
+    pub fn new(config: &VrfConfig, ledger: Arc<Ledger>) -> anyhow::Result<Self> {
+        Ok(Self {
+            ledger,
+            oracle: config.oracle,
+            vrf_public_key: hex::encode(config.vrf_public_key()?),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OracleJson {
+    pub oracle: String,
+    pub vrf_public_key: String,
+}
+
+/// The requests matching a lookup, by VRF account, round and batch slot.
+#[derive(Debug, Serialize)]
+pub struct RequestsJson {
+    pub oracle: String,
+    pub vrf_public_key: String,
+    pub requests: Vec<RequestJson>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestJson {
+    pub vrf: String,
+    pub round: u64,
+    pub batch_slot: u8,
+    pub program_id: String,
+    pub status: &'static str,
+    pub request_transaction: String,
+    pub request_slot: u64,
+    pub seeds: String,
+    /// `None` until the response is on-chain, so a result cannot be seen before it lands.
+    pub proof: Option<String>,
+    /// The VRF output hash, the random value supplied to the callback,
+    /// `None` until the response is on-chain.
+    pub result: Option<String>,
+    pub response_transaction: Option<String>,
+}
+
+impl From<LedgerEntry> for RequestJson {
+    fn from(entry: LedgerEntry) -> Self {
+        let fulfilled = entry.status == LedgerStatus::Fulfilled;
+        Self {
+            vrf: entry.vrf.to_string(),
+            round: entry.round,
+            batch_slot: entry.batch_slot,
+            program_id: entry.program_id.to_string(),
+            status: entry.status.as_str(),
+            request_transaction: entry.request_signature,
+            request_slot: entry.request_slot,
+            seeds: hex::encode(entry.seeds),
+            proof: fulfilled.then(|| hex::encode(entry.proof)),
+            result: fulfilled.then(|| hex::encode(entry.result)),
+            response_transaction: entry.response_signature,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    InvalidPubkey(String),
+    NotFound,
+    Ledger(anyhow::Error),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            Self::InvalidPubkey(key) => (StatusCode::BAD_REQUEST, format!("invalid pubkey: {key}")),
+            Self::NotFound => (StatusCode::NOT_FOUND, "not found".to_string()),
+            Self::Ledger(err) => {
+                tracing::error!("Error reading ledger:\n{err:#}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "ledger error".to_string(),
+                )
+            }
+        };
+
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+pub fn router(state: Arc<ApiState>) -> Router {
+    Router::new()
+        .route("/oracle", get(oracle))
+        .route("/vrf/:vrf", get(vrf_requests))
+        .route("/transaction/:signature", get(transaction_requests))
+        .with_state(state)
+}
+
+/// Serve the API on `address` until the server fails.
+pub async fn serve(address: SocketAddr, state: Arc<ApiState>) -> anyhow::Result<()> {
+    tracing::info!("Serving API on: {address}");
+    axum::Server::try_bind(&address)?
+        .serve(router(state).into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+pub async fn oracle(State(state): State<Arc<ApiState>>) -> Json<OracleJson> {
+    Json(OracleJson {
+        oracle: state.oracle.to_string(),
+        vrf_public_key: state.vrf_public_key.clone(),
+    })
+}
+
+pub async fn vrf_requests(
+    State(state): State<Arc<ApiState>>,
+    Path(vrf): Path<String>,
+) -> Result<Json<RequestsJson>, ApiError> {
+    let vrf = Pubkey::from_str(&vrf).map_err(|_| ApiError::InvalidPubkey(vrf))?;
+    requests(&state, state.ledger.entries(&vrf))
+}
+
+pub async fn transaction_requests(
+    State(state): State<Arc<ApiState>>,
+    Path(signature): Path<String>,
+) -> Result<Json<RequestsJson>, ApiError> {
+    requests(&state, state.ledger.entries_by_signature(&signature))
+}
+
+fn requests(
+    state: &ApiState,
+    entries: anyhow::Result<Vec<LedgerEntry>>,
+) -> Result<Json<RequestsJson>, ApiError> {
+    let entries = entries.map_err(ApiError::Ledger)?;
+    if entries.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(RequestsJson {
+        oracle: state.oracle.to_string(),
+        vrf_public_key: state.vrf_public_key.clone(),
+        requests: entries.into_iter().map(RequestJson::from).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use vrf_sdk::vrf::{PROOF_BYTE_LEN, RESULT_BYTE_LEN, SEEDS_BYTE_LEN};
+
+    use super::*;
+    use crate::{ledger::LedgerRequest, process::VrfOutput};
+
+    fn state() -> Arc<ApiState> {
+        Arc::new(ApiState {
+            ledger: Arc::new(Ledger::open_in_memory().unwrap()),
+            oracle: Pubkey::new_unique(),
+            vrf_public_key: "02ab".to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_lookup_requests() {
+        let state = state();
+        let vrf = Pubkey::new_unique();
+        state
+            .ledger
+            .record_request(
+                &LedgerRequest {
+                    vrf,
+                    round: 1,
+                    program_id: Pubkey::new_unique(),
+                    signature: "request",
+                    slot: 42,
+                },
+                &[VrfOutput {
+                    seeds: [1; SEEDS_BYTE_LEN],
+                    result: [2; RESULT_BYTE_LEN],
+                    proof: [3; PROOF_BYTE_LEN],
+                }],
+            )
+            .unwrap();
+        state
+            .ledger
+            .record_response(&vrf, 1, 0, "response")
+            .unwrap();
+
+        let Json(by_vrf) = vrf_requests(State(state.clone()), Path(vrf.to_string()))
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&by_vrf).unwrap();
+        assert_eq!(json["vrf_public_key"], "02ab");
+        assert_eq!(json["oracle"], state.oracle.to_string());
+        let request = &json["requests"][0];
+        assert_eq!(request["vrf"], vrf.to_string());
+        assert_eq!(request["status"], "fulfilled");
+        assert_eq!(request["seeds"], "01".repeat(SEEDS_BYTE_LEN));
+        assert_eq!(request["result"], "02".repeat(RESULT_BYTE_LEN));
+        assert_eq!(request["proof"], "03".repeat(PROOF_BYTE_LEN));
+        assert_eq!(request["request_transaction"], "request");
+        assert_eq!(request["response_transaction"], "response");
+
+        for signature in ["request", "response"] {
+            let Json(by_signature) =
+                transaction_requests(State(state.clone()), Path(signature.to_string()))
+                    .await
+                    .unwrap();
+            assert_eq!(serde_json::to_value(&by_signature).unwrap(), json);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup_errors() {
+        let state = state();
+
+        let err = vrf_requests(State(state.clone()), Path("not a pubkey".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+
+        let err = vrf_requests(State(state.clone()), Path(Pubkey::new_unique().to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+
+        let err = transaction_requests(State(state), Path("unknown".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,13 +1,16 @@
+use std::net::SocketAddr;
+
 use anchor_client::{
     solana_sdk::{
         commitment_config::{CommitmentConfig, CommitmentLevel},
         pubkey::Pubkey,
-        signature::Keypair,
+        signature::{Keypair, Signer},
     },
     Cluster,
 };
 use anyhow::Context;
 use serde_with::{serde_as, DisplayFromStr};
+use vrf::openssl::{CipherSuite, ECVRF};
 
 #[serde_as]
 #[derive(Debug, serde::Deserialize, Clone)]
@@ -23,15 +26,72 @@ pub struct Config {
     commitment: CommitmentLevel,
     #[serde_as(as = "Vec<DisplayFromStr>")]
     program_ids: Vec<Pubkey>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    callback_program_ids: Vec<Pubkey>,
+    #[serde(default)]
+    fee: u64,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    fee_mint: Option<Pubkey>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    fee_vault: Option<Pubkey>,
+    #[serde(default = "default_ledger_path")]
+    ledger_path: String,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    api_address: Option<SocketAddr>,
+    #[serde(default = "default_backfill_concurrency")]
+    backfill_concurrency: usize,
+}
+
+fn default_backfill_concurrency() -> usize {
+    4
+}
+
+fn default_ledger_path() -> String {
+    "vrf-ledger.sqlite".to_string()
 }
 
 #[derive(Debug)]
 pub struct VrfConfig {
     pub signer: Keypair,
+    /// Our [`Oracle`](`vrf_sdk::oracle::Oracle`) account, derived from the signer.
+    pub oracle: Pubkey,
     pub vrf_secret: Vec<u8>,
     pub cluster: Cluster,
     pub commitment: CommitmentConfig,
     pub program_ids: Vec<Pubkey>,
+    /// Programs a callback may target besides the requesting program.
+    pub callback_program_ids: Vec<Pubkey>,
+    /// Fee of each request, in lamports or in the smallest unit of `fee_mint`.
+    pub fee: u64,
+    /// The SPL token the fee is paid in, `Pubkey::default()` for lamports.
+    pub fee_mint: Pubkey,
+    /// Who receive the fees, the signer by default.
+    pub fee_vault: Pubkey,
+    /// The SQLite database recording every request we fulfill, see [`Ledger`](`crate::ledger::Ledger`).
+    pub ledger_path: String,
+    /// Where the public HTTP API listen, disabled if not set, see [`api`](`crate::api`).
+    pub api_address: Option<SocketAddr>,
+    /// How many old transactions are processed at once.
+    pub backfill_concurrency: usize,
+}
+
+impl VrfConfig {
+    /// The compressed public key that consumer programs use to verify our proofs.
+    pub fn vrf_public_key(&self) -> anyhow::Result<Vec<u8>> {
+        let mut vrf = ECVRF::from_suite(CipherSuite::SECP256K1_SHA256_TAI)
+            .context("create ECVRF instance")?;
+        vrf.derive_public_key(&self.vrf_secret)
+            .context("derive VRF public key")
+    }
+
+    /// Whether a request from `requester` may be fulfilled by invoking `callback_program`.
+    pub fn is_callback_allowed(&self, requester: &Pubkey, callback_program: &Pubkey) -> bool {
+        callback_program == requester || self.callback_program_ids.contains(callback_program)
+    }
 }
 
 impl TryFrom<Config> for VrfConfig {
@@ -46,11 +106,19 @@ impl TryFrom<Config> for VrfConfig {
         };
 
         Ok(Self {
+            oracle: vrf_sdk::oracle::address(&owner.pubkey()),
+            fee: config.fee,
+            fee_mint: config.fee_mint.unwrap_or_default(),
+            fee_vault: config.fee_vault.unwrap_or_else(|| owner.pubkey()),
             signer: owner,
             vrf_secret: config.vrf_secret,
             cluster: config.cluster,
             commitment,
             program_ids: config.program_ids,
+            callback_program_ids: config.callback_program_ids,
+            ledger_path: config.ledger_path,
+            api_address: config.api_address,
+            backfill_concurrency: config.backfill_concurrency.max(1),
         })
     }
 }
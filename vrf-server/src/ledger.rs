@@ -0,0 +1,615 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use vrf_sdk::__private::Pubkey;
+
+use crate::process::VrfOutput;
+
+/// Where a request stands in the ledger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerStatus {
+    /// The proof is computed, the response is not confirmed yet.
+    Pending,
+    /// The response transaction is confirmed.
+    Fulfilled,
+    /// The requester cancelled the request.
+    Cancelled,
+}
+
+impl LedgerStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Fulfilled => "fulfilled",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for LedgerStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "fulfilled" => Ok(Self::Fulfilled),
+            "cancelled" => Ok(Self::Cancelled),
+            _ => Err(anyhow::anyhow!("unknown ledger status: {s}")),
+        }
+    }
+}
+
+/// A request as found in the logs of its transaction.
+pub struct LedgerRequest<'a> {
+    pub vrf: Pubkey,
+    pub round: u64,
+    pub program_id: Pubkey,
+    /// The transaction emitting the request.
+    pub signature: &'a str,
+    /// The slot of the request transaction.
+    pub slot: u64,
+}
+
+/// One output of a request, a batch request has one entry per slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub vrf: Pubkey,
+    pub round: u64,
+    pub batch_slot: u8,
+    pub program_id: Pubkey,
+    pub request_signature: String,
+    pub request_slot: u64,
+    pub seeds: Vec<u8>,
+    pub proof: Vec<u8>,
+    pub result: Vec<u8>,
+    pub response_signature: Option<String>,
+    pub status: LedgerStatus,
+}
+
+/// The last transaction of a program processed, where the backfill resumes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerCursor {
+    pub signature: String,
+    pub slot: u64,
+}
+
+/// The transactions of a program handed to processing, so its cursor only moves past a slot
+/// once every transaction of that slot and before is done.
+///
+/// After a transaction failed, the cursor stays before it until the next backfill retries it.
+#[derive(Debug, Default)]
+pub struct PendingCursors {
+    /// The progress of each transaction, by slot then signature.
+    transactions: BTreeMap<(u64, String), Progress>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Progress {
+    Started,
+    Done,
+    Failed,
+}
+
+impl PendingCursors {
+    /// Track a transaction handed to processing, a failed one is started again.
+    pub fn start(&mut self, cursor: &LedgerCursor) {
+        let progress = self
+            .transactions
+            .entry((cursor.slot, cursor.signature.clone()))
+            .or_insert(Progress::Started);
+        if *progress == Progress::Failed {
+            *progress = Progress::Started;
+        }
+    }
+
+    pub fn finish(&mut self, cursor: &LedgerCursor) {
+        self.set(cursor, Progress::Done);
+    }
+
+    pub fn fail(&mut self, cursor: &LedgerCursor) {
+        self.set(cursor, Progress::Failed);
+    }
+
+    fn set(&mut self, cursor: &LedgerCursor, progress: Progress) {
+        if let Some(current) = self
+            .transactions
+            .get_mut(&(cursor.slot, cursor.signature.clone()))
+        {
+            *current = progress;
+        }
+    }
+
+    /// Whether a transaction failed and was not started again since.
+    pub fn has_failed(&self) -> bool {
+        self.transactions
+            .values()
+            .any(|progress| *progress == Progress::Failed)
+    }
+
+    /// The transactions the cursor may move to, oldest first:
+    /// those done in a slot older than any transaction not done yet.
+    pub fn done(&self) -> Vec<LedgerCursor> {
+        let pending_slot = self
+            .transactions
+            .iter()
+            .find(|(_, progress)| **progress != Progress::Done)
+            .map(|((slot, _), _)| *slot);
+
+        self.transactions
+            .keys()
+            .take_while(|(slot, _)| !matches!(pending_slot, Some(pending) if *slot >= pending))
+            .map(|(slot, signature)| LedgerCursor {
+                signature: signature.clone(),
+                slot: *slot,
+            })
+            .collect()
+    }
+
+    /// Forget the transactions up to `cursor` once it is persisted.
+    pub fn persisted(&mut self, cursor: &LedgerCursor) {
+        self.transactions.retain(|(slot, _), _| *slot > cursor.slot);
+    }
+}
+
+/// Persistent record of every request we fulfill, so the seeds,
+/// proofs and results stay public after the fact.
+pub struct Ledger {
+    conn: Mutex<Connection>,
+    /// The requests being fulfilled, by vrf account and round.
+    in_flight: Mutex<HashSet<(Pubkey, u64)>>,
+}
+
+/// A request claimed by [`Ledger::claim`], released on drop.
+pub struct InFlight<'a> {
+    ledger: &'a Ledger,
+    key: (Pubkey, u64),
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.ledger.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl Ledger {
+    /// Open the SQLite ledger at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let conn =
+            Connection::open(path).with_context(|| format!("Open ledger {}", path.display()))?;
+        Self::new(conn)
+    }
+
+    /// A ledger kept in memory only.
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS requests (
+                vrf TEXT NOT NULL,
+                round INTEGER NOT NULL,
+                batch_slot INTEGER NOT NULL,
+                program_id TEXT NOT NULL,
+                request_signature TEXT NOT NULL,
+                request_slot INTEGER NOT NULL,
+                seeds BLOB NOT NULL,
+                proof BLOB NOT NULL,
+                result BLOB NOT NULL,
+                response_signature TEXT,
+                status TEXT NOT NULL,
+                PRIMARY KEY (vrf, round, batch_slot)
+            );
+            CREATE INDEX IF NOT EXISTS requests_response_signature ON requests (response_signature);
+            CREATE INDEX IF NOT EXISTS requests_request_signature ON requests (request_signature);
+            CREATE TABLE IF NOT EXISTS cursors (
+                program_id TEXT PRIMARY KEY,
+                signature TEXT NOT NULL,
+                slot INTEGER NOT NULL
+            );",
+        )
+        .context("Create ledger tables")?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            in_flight: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Claim the request of `round` for the caller until the guard is dropped,
+    /// `None` if another task is already fulfilling it.
+    pub fn claim(&self, vrf: &Pubkey, round: u64) -> Option<InFlight<'_>> {
+        let key = (*vrf, round);
+        if !self.in_flight.lock().unwrap().insert(key) {
+            return None;
+        }
+
+        Some(InFlight { ledger: self, key })
+    }
+
+    /// Record the outputs of a request before sending the response,
+    /// a request processed again keeps its response.
+    pub fn record_request(
+        &self,
+        request: &LedgerRequest<'_>,
+        outputs: &[VrfOutput],
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (batch_slot, output) in outputs.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO requests (
+                    vrf, round, batch_slot, program_id, request_signature, request_slot,
+                    seeds, proof, result, status
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                ON CONFLICT (vrf, round, batch_slot) DO UPDATE SET
+                    seeds = excluded.seeds, proof = excluded.proof, result = excluded.result",
+                params![
+                    request.vrf.to_string(),
+                    request.round,
+                    batch_slot as u8,
+                    request.program_id.to_string(),
+                    request.signature,
+                    request.slot,
+                    &output.seeds[..],
+                    &output.proof[..],
+                    &output.result[..],
+                    LedgerStatus::Pending.as_str(),
+                ],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// The response of a request, or of one `batch_slot` of a batch request, is confirmed.
+    pub fn record_response(
+        &self,
+        vrf: &Pubkey,
+        round: u64,
+        batch_slot: u8,
+        signature: &str,
+    ) -> anyhow::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE requests SET response_signature = ?4, status = ?5 WHERE vrf = ?1 AND round = ?2 AND batch_slot = ?3",
+            params![
+                vrf.to_string(),
+                round,
+                batch_slot,
+                signature,
+                LedgerStatus::Fulfilled.as_str()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// The pending requests of `vrf` will not be fulfilled anymore,
+    /// only those before `rearmed_round` if the account was re-armed since.
+    pub fn record_cancelled(&self, vrf: &Pubkey, rearmed_round: Option<u64>) -> anyhow::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE requests SET status = ?3
+            WHERE vrf = ?1 AND status = ?2 AND (?4 IS NULL OR round < ?4)",
+            params![
+                vrf.to_string(),
+                LedgerStatus::Pending.as_str(),
+                LedgerStatus::Cancelled.as_str(),
+                rearmed_round,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Where the backfill of `program_id` resumes, `None` before its first transaction.
+    pub fn cursor(&self, program_id: &Pubkey) -> anyhow::Result<Option<LedgerCursor>> {
+        let cursor = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT signature, slot FROM cursors WHERE program_id = ?1",
+                params![program_id.to_string()],
+                |row| {
+                    Ok(LedgerCursor {
+                        signature: row.get(0)?,
+                        slot: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(cursor)
+    }
+
+    /// Move the cursor of `program_id` forward, a cursor at an older slot is ignored.
+    pub fn set_cursor(&self, program_id: &Pubkey, cursor: &LedgerCursor) -> anyhow::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO cursors (program_id, signature, slot) VALUES (?1, ?2, ?3)
+            ON CONFLICT (program_id) DO UPDATE SET signature = excluded.signature, slot = excluded.slot
+            WHERE excluded.slot >= cursors.slot",
+            params![program_id.to_string(), cursor.signature, cursor.slot],
+        )?;
+
+        Ok(())
+    }
+
+    /// Every entry of `vrf`, by round then batch slot.
+    pub fn entries(&self, vrf: &Pubkey) -> anyhow::Result<Vec<LedgerEntry>> {
+        self.query("WHERE vrf = ?1", &vrf.to_string())
+    }
+
+    /// The entries requested or fulfilled by the transaction `signature`.
+    pub fn entries_by_signature(&self, signature: &str) -> anyhow::Result<Vec<LedgerEntry>> {
+        self.query(
+            "WHERE request_signature = ?1 OR response_signature = ?1",
+            signature,
+        )
+    }
+
+    /// The entry of `batch_slot` in the given round, if recorded.
+    pub fn entry(
+        &self,
+        vrf: &Pubkey,
+        round: u64,
+        batch_slot: u8,
+    ) -> anyhow::Result<Option<LedgerEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let entry = conn
+            .query_row(
+                &format!("{SELECT_ENTRY} WHERE vrf = ?1 AND round = ?2 AND batch_slot = ?3"),
+                params![vrf.to_string(), round, batch_slot],
+                row_to_entry,
+            )
+            .optional()?;
+
+        entry.transpose()
+    }
+
+    fn query(&self, filter: &str, value: &str) -> anyhow::Result<Vec<LedgerEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{SELECT_ENTRY} {filter} ORDER BY round, batch_slot"
+        ))?;
+        let entries = stmt.query_map(params![value], row_to_entry)?;
+
+        entries
+            .map(|entry| entry.map_err(anyhow::Error::from).and_then(|entry| entry))
+            .collect()
+    }
+}
+
+const SELECT_ENTRY: &str =
+    "SELECT vrf, round, batch_slot, program_id, request_signature, request_slot,
+    seeds, proof, result, response_signature, status FROM requests";
+
+/// The columns are checked after the query, so a corrupted row is reported instead of skipped.
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<anyhow::Result<LedgerEntry>> {
+    let vrf: String = row.get(0)?;
+    let program_id: String = row.get(3)?;
+    let status: String = row.get(10)?;
+    let mut entry = LedgerEntry {
+        vrf: Pubkey::default(),
+        round: row.get(1)?,
+        batch_slot: row.get(2)?,
+        program_id: Pubkey::default(),
+        request_signature: row.get(4)?,
+        request_slot: row.get(5)?,
+        seeds: row.get(6)?,
+        proof: row.get(7)?,
+        result: row.get(8)?,
+        response_signature: row.get(9)?,
+        status: LedgerStatus::Pending,
+    };
+
+    Ok((|| {
+        entry.vrf = Pubkey::from_str(&vrf).context("Invalid vrf in ledger")?;
+        entry.program_id = Pubkey::from_str(&program_id).context("Invalid program_id in ledger")?;
+        entry.status = status.parse()?;
+        Ok(entry)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use vrf_sdk::vrf::{PROOF_BYTE_LEN, RESULT_BYTE_LEN, SEEDS_BYTE_LEN};
+
+    use super::*;
+
+    fn output(value: u8) -> VrfOutput {
+        VrfOutput {
+            seeds: [value; SEEDS_BYTE_LEN],
+            result: [value; RESULT_BYTE_LEN],
+            proof: [value; PROOF_BYTE_LEN],
+        }
+    }
+
+    fn request(vrf: Pubkey, round: u64) -> LedgerRequest<'static> {
+        LedgerRequest {
+            vrf,
+            round,
+            program_id: Pubkey::new_unique(),
+            signature: "request",
+            slot: 42,
+        }
+    }
+
+    #[test]
+    fn test_request_lifecycle() {
+        let ledger = Ledger::open_in_memory().unwrap();
+        let vrf = Pubkey::new_unique();
+
+        ledger
+            .record_request(&request(vrf, 1), &[output(1)])
+            .unwrap();
+        let entry = ledger.entry(&vrf, 1, 0).unwrap().unwrap();
+        assert_eq!(entry.status, LedgerStatus::Pending);
+        assert_eq!(entry.request_slot, 42);
+        assert_eq!(entry.seeds, [1; SEEDS_BYTE_LEN]);
+        assert_eq!(entry.proof, [1; PROOF_BYTE_LEN]);
+        assert_eq!(entry.response_signature, None);
+
+        ledger.record_response(&vrf, 1, 0, "response").unwrap();
+        let entries = ledger.entries_by_signature("response").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, LedgerStatus::Fulfilled);
+        assert_eq!(ledger.entries_by_signature("request").unwrap(), entries);
+
+        // processed again, the response is kept
+        ledger
+            .record_request(&request(vrf, 1), &[output(1)])
+            .unwrap();
+        let entry = ledger.entry(&vrf, 1, 0).unwrap().unwrap();
+        assert_eq!(entry.status, LedgerStatus::Fulfilled);
+        assert_eq!(entry.response_signature.as_deref(), Some("response"));
+
+        // a fulfilled request is not cancelled
+        ledger.record_cancelled(&vrf, None).unwrap();
+        assert_eq!(
+            ledger.entry(&vrf, 1, 0).unwrap().unwrap().status,
+            LedgerStatus::Fulfilled
+        );
+    }
+
+    #[test]
+    fn test_claim_in_flight() {
+        let ledger = Ledger::open_in_memory().unwrap();
+        let vrf = Pubkey::new_unique();
+
+        let claimed = ledger.claim(&vrf, 1).unwrap();
+        assert!(ledger.claim(&vrf, 1).is_none());
+        // another round, or another account, is independent
+        assert!(ledger.claim(&vrf, 2).is_some());
+        assert!(ledger.claim(&Pubkey::new_unique(), 1).is_some());
+
+        drop(claimed);
+        assert!(ledger.claim(&vrf, 1).is_some());
+    }
+
+    #[test]
+    fn test_cursor() {
+        let ledger = Ledger::open_in_memory().unwrap();
+        let program_id = Pubkey::new_unique();
+        let cursor = |signature: &str, slot| LedgerCursor {
+            signature: signature.to_string(),
+            slot,
+        };
+
+        assert_eq!(ledger.cursor(&program_id).unwrap(), None);
+
+        ledger.set_cursor(&program_id, &cursor("a", 10)).unwrap();
+        ledger.set_cursor(&program_id, &cursor("b", 12)).unwrap();
+        assert_eq!(ledger.cursor(&program_id).unwrap(), Some(cursor("b", 12)));
+
+        // never moves backward
+        ledger.set_cursor(&program_id, &cursor("c", 11)).unwrap();
+        assert_eq!(ledger.cursor(&program_id).unwrap(), Some(cursor("b", 12)));
+
+        assert_eq!(ledger.cursor(&Pubkey::new_unique()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_pending_cursors() {
+        let cursor = |signature: &str, slot| LedgerCursor {
+            signature: signature.to_string(),
+            slot,
+        };
+        let mut pending = PendingCursors::default();
+
+        for (signature, slot) in [("a", 10), ("b", 11), ("c", 11), ("d", 12)] {
+            pending.start(&cursor(signature, slot));
+        }
+        assert!(pending.done().is_empty());
+
+        // slot 11 is not complete until "c" is done
+        pending.finish(&cursor("a", 10));
+        pending.finish(&cursor("b", 11));
+        pending.finish(&cursor("d", 12));
+        assert_eq!(pending.done(), vec![cursor("a", 10)]);
+
+        pending.finish(&cursor("c", 11));
+        assert_eq!(pending.done().last(), Some(&cursor("d", 12)));
+
+        pending.persisted(&cursor("c", 11));
+        assert_eq!(pending.done(), vec![cursor("d", 12)]);
+
+        // a failed transaction holds the cursor back, the newer ones are still tracked
+        pending.start(&cursor("e", 13));
+        pending.start(&cursor("f", 14));
+        pending.fail(&cursor("e", 13));
+        pending.finish(&cursor("f", 14));
+        pending.start(&cursor("g", 15));
+        assert!(pending.has_failed());
+        assert_eq!(pending.done(), vec![cursor("d", 12)]);
+
+        // until the backfill retries it
+        pending.start(&cursor("e", 13));
+        pending.start(&cursor("f", 14));
+        assert!(!pending.has_failed());
+        assert_eq!(pending.done(), vec![cursor("d", 12)]);
+        pending.finish(&cursor("e", 13));
+        assert_eq!(pending.done().last(), Some(&cursor("f", 14)));
+        pending.finish(&cursor("g", 15));
+        assert_eq!(pending.done().last(), Some(&cursor("g", 15)));
+    }
+
+    #[test]
+    fn test_cancelled_then_rearmed() {
+        let ledger = Ledger::open_in_memory().unwrap();
+        let vrf = Pubkey::new_unique();
+
+        ledger
+            .record_request(&request(vrf, 1), &[output(1)])
+            .unwrap();
+        ledger
+            .record_request(&request(vrf, 2), &[output(2)])
+            .unwrap();
+        // cancelled and re-armed for round 2 in the same transaction, processed again
+        ledger.record_cancelled(&vrf, Some(2)).unwrap();
+
+        let status = |round| ledger.entry(&vrf, round, 0).unwrap().unwrap().status;
+        assert_eq!(status(1), LedgerStatus::Cancelled);
+        assert_eq!(status(2), LedgerStatus::Pending);
+    }
+
+    #[test]
+    fn test_batch_and_rounds() {
+        let ledger = Ledger::open_in_memory().unwrap();
+        let vrf = Pubkey::new_unique();
+
+        ledger
+            .record_request(&request(vrf, 2), &[output(3), output(4)])
+            .unwrap();
+        ledger
+            .record_request(&request(vrf, 1), &[output(1)])
+            .unwrap();
+        // each slot of a batch is fulfilled by its own transaction
+        ledger.record_response(&vrf, 2, 0, "response").unwrap();
+        ledger.record_cancelled(&vrf, None).unwrap();
+
+        let entries = ledger.entries(&vrf).unwrap();
+        let keys = entries
+            .iter()
+            .map(|entry| (entry.round, entry.batch_slot, entry.result[0], entry.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                (1, 0, 1, LedgerStatus::Cancelled),
+                (2, 0, 3, LedgerStatus::Fulfilled),
+                (2, 1, 4, LedgerStatus::Cancelled)
+            ]
+        );
+
+        assert!(ledger.entries(&Pubkey::new_unique()).unwrap().is_empty());
+        assert!(ledger.entry(&vrf, 3, 0).unwrap().is_none());
+    }
+}
@@ -1,4 +1,8 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anchor_client::solana_client::{
     nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
@@ -9,13 +13,25 @@ use backoff::ExponentialBackoff;
 use futures_util::StreamExt;
 use vrf_sdk::__private::Pubkey;
 
-use crate::{process::process, process_old_trans::process_old_transaction};
+use crate::{
+    api::ApiState,
+    config::VrfConfig,
+    ledger::{Ledger, LedgerCursor, PendingCursors},
+    process::{process, LogsTransaction, Processed, ProcessedRequest},
+    process_old_trans::{backfill, persist_cursor},
+};
 
+mod api;
 mod config;
+mod ledger;
+mod oracle;
 mod parse_logs;
 mod process;
 mod process_old_trans;
 
+/// How often the failed transactions are retried.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
@@ -32,6 +48,9 @@ async fn main() -> anyhow::Result<()> {
     println!("Running VRF handler with:");
     println!("Cluster: ({}) {}", &config.cluster, config.cluster.url());
     println!("Commitment: {}", &config.commitment.commitment);
+    println!("VRF public key: {:?}", &config.vrf_public_key()?);
+    println!("Oracle: {}", &config.oracle);
+    println!("Ledger: {}", &config.ledger_path);
     println!("---");
 
     let rpc_client = Arc::new(RpcClient::new_with_commitment(
@@ -39,7 +58,18 @@ async fn main() -> anyhow::Result<()> {
         config.commitment,
     ));
 
-    tokio::spawn(process_old_transaction(config.clone(), rpc_client.clone()));
+    crate::oracle::ensure_registered(&config, &rpc_client).await?;
+
+    let ledger = Arc::new(Ledger::open(&config.ledger_path)?);
+
+    if let Some(address) = config.api_address {
+        let state = Arc::new(ApiState::new(&config, ledger.clone())?);
+        tokio::spawn(async move {
+            if let Err(err) = crate::api::serve(address, state).await {
+                tracing::error!("API server stopped:\n{err:#}");
+            }
+        });
+    }
 
     let handles = config
         .program_ids
@@ -49,6 +79,7 @@ async fn main() -> anyhow::Result<()> {
                 config.clone(),
                 Arc::new(program_id.to_string()),
                 rpc_client.clone(),
+                ledger.clone(),
             ))
         })
         .collect::<Vec<_>>();
@@ -61,9 +92,23 @@ pub async fn logs_subscribe(
     config: Arc<crate::config::VrfConfig>,
     program_id: Arc<String>,
     rpc_client: Arc<RpcClient>,
+    ledger: Arc<Ledger>,
 ) -> ! {
     let program_id_pubkey =
         Pubkey::from_str(&program_id).expect(&format!("invalid program id: {}", &program_id));
+    // The last transaction handed to processing, where the backfill of a (re)connect starts from
+    let last_processed = Mutex::new(
+        ledger
+            .cursor(&program_id_pubkey)
+            .unwrap_or_else(|err| panic!("invalid cursor of {}: {err:#}", &program_id)),
+    );
+    let pending = Arc::new(Mutex::new(PendingCursors::default()));
+    tokio::spawn(persist_cursor(
+        rpc_client.clone(),
+        ledger.clone(),
+        program_id_pubkey,
+        pending.clone(),
+    ));
 
     loop {
         let _ = backoff::future::retry::<Result<(), backoff::Error<()>>, _, _, _, _>(
@@ -86,15 +131,61 @@ pub async fn logs_subscribe(
                 };
 
                 tracing::info!("Listening for logs from: {}", &program_id);
-                while let Some(response) = recv_stream.next().await {
+
+                // Subscribed first, so the live logs take over exactly where the backfill ends,
+                // a transaction in both is fulfilled once
+                catch_up(
+                    &config,
+                    &rpc_client,
+                    &ledger,
+                    &program_id_pubkey,
+                    &last_processed,
+                    &pending,
+                )
+                .await;
+
+                // A failed transaction is retried without waiting for a reconnect
+                let mut retry = tokio::time::interval(RETRY_INTERVAL);
+                loop {
+                    let response = tokio::select! {
+                        response = recv_stream.next() => match response {
+                            Some(response) => response,
+                            None => break,
+                        },
+                        _ = retry.tick() => {
+                            if pending.lock().unwrap().has_failed() {
+                                catch_up(
+                                    &config,
+                                    &rpc_client,
+                                    &ledger,
+                                    &program_id_pubkey,
+                                    &last_processed,
+                                    &pending,
+                                )
+                                .await;
+                            }
+                            continue;
+                        }
+                    };
+
+                    let cursor = LedgerCursor {
+                        signature: response.value.signature.clone(),
+                        slot: response.context.slot,
+                    };
+                    advance(&last_processed, cursor.clone());
+                    pending.lock().unwrap().start(&cursor);
+
                     let config = config.clone();
                     let rpc_client = rpc_client.clone();
+                    let ledger = ledger.clone();
                     let program_id = program_id.clone();
+                    let pending = pending.clone();
 
                     // Spawn a new task to handle the transaction
                     tokio::spawn(async move {
                         let program_id: &str = &program_id;
 
+                        let slot = response.context.slot;
                         let RpcLogsResponse {
                             signature,
                             err,
@@ -111,22 +202,72 @@ pub async fn logs_subscribe(
                             span.in_scope(|| {
                                 tracing::info!("Skipping error transaction:\n{err:#}")
                             });
+                            pending.lock().unwrap().finish(&cursor);
                             return;
                         }
 
                         span.in_scope(|| tracing::info!("Start processing"));
-                        match process(&config, &rpc_client, &program_id_pubkey, &span, &logs).await
-                        {
-                            Ok(_) => {
-                                // TODO
-                                span.in_scope(|| tracing::info!("Finished!"));
-                            }
+                        let transaction = LogsTransaction {
+                            signature: &signature,
+                            slot,
+                        };
+                        let processed = process(
+                            &config,
+                            &rpc_client,
+                            &ledger,
+                            &program_id_pubkey,
+                            &transaction,
+                            &span,
+                            &logs,
+                        )
+                        .await;
+                        let processed = match processed {
+                            Ok(processed) => processed,
                             Err(err) => {
+                                pending.lock().unwrap().fail(&cursor);
                                 span.in_scope(|| {
                                     tracing::error!("Error processing transaction:\n{err:#}")
                                 });
+                                return;
+                            }
+                        };
+
+                        // a failed request is retried with its transaction, the others are then skipped
+                        if processed.iter().any(|request| request.processed.is_err()) {
+                            pending.lock().unwrap().fail(&cursor);
+                        } else {
+                            pending.lock().unwrap().finish(&cursor);
+                        }
+                        for ProcessedRequest {
+                            vrf,
+                            round,
+                            processed,
+                        } in processed
+                        {
+                            match processed {
+                                Ok(Processed::Fulfilled(response)) => {
+                                    span.in_scope(|| {
+                                        tracing::info!(
+                                            "Request {vrf} ({round}) fulfilled by: {}",
+                                            response.response_transactions.join(", ")
+                                        )
+                                    });
+                                }
+                                Ok(Processed::Skipped(reason)) => {
+                                    span.in_scope(|| {
+                                        tracing::info!("Skipping request {vrf} ({round}): {reason}")
+                                    });
+                                }
+                                Err(err) => {
+                                    span.in_scope(|| {
+                                        tracing::error!(
+                                            "Error processing request {vrf} ({round}):\n{err:#}"
+                                        )
+                                    });
+                                }
                             }
                         }
+                        span.in_scope(|| tracing::info!("Finished!"));
                     });
                 }
 
@@ -144,3 +285,57 @@ pub async fn logs_subscribe(
         .await;
     }
 }
+
+/// Backfill the transactions of `program_id` since `last_processed`, or retry the failed ones.
+async fn catch_up(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_id: &Pubkey,
+    last_processed: &Mutex<Option<LedgerCursor>>,
+    pending: &Mutex<PendingCursors>,
+) {
+    let since = last_processed.lock().unwrap().clone();
+    match backfill(
+        config,
+        rpc_client,
+        ledger,
+        program_id,
+        since.as_ref(),
+        pending,
+    )
+    .await
+    {
+        Ok(Some(newest)) => advance(last_processed, newest),
+        Ok(None) => {}
+        Err(err) => tracing::error!("Process old transaction of {program_id} failed:\n{err:#}"),
+    }
+}
+
+/// Move `last_processed` forward to `cursor`, unless it is already at a newer slot.
+fn advance(last_processed: &Mutex<Option<LedgerCursor>>, cursor: LedgerCursor) {
+    let mut last_processed = last_processed.lock().unwrap();
+    if !matches!(&*last_processed, Some(last) if last.slot > cursor.slot) {
+        *last_processed = Some(cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_last_processed() {
+        let cursor = |signature: &str, slot| LedgerCursor {
+            signature: signature.to_string(),
+            slot,
+        };
+        let last_processed = Mutex::new(None);
+
+        advance(&last_processed, cursor("a", 10));
+        advance(&last_processed, cursor("b", 12));
+        // a notification of an older slot arriving late
+        advance(&last_processed, cursor("c", 11));
+        assert_eq!(*last_processed.lock().unwrap(), Some(cursor("b", 12)));
+    }
+}
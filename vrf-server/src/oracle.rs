@@ -0,0 +1,109 @@
+use anchor_client::{
+    anchor_lang::{AccountDeserialize, AnchorSerialize},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Signer,
+        system_program,
+        transaction::Transaction,
+    },
+};
+use anyhow::Context;
+use vrf_sdk::{
+    coordinator,
+    oracle::{Oracle, OracleStatus},
+};
+
+use crate::config::VrfConfig;
+
+/// Register our oracle in the coordinator registry if it does not exist yet,
+/// and update its fee to match the configuration.
+///
+/// Requests are bound to an oracle, so this must be done before accepting any request.
+pub async fn ensure_registered(config: &VrfConfig, rpc_client: &RpcClient) -> anyhow::Result<()> {
+    let vrf_public_key = config.vrf_public_key()?;
+
+    let account = rpc_client
+        .get_account_with_commitment(&config.oracle, config.commitment)
+        .await?
+        .value;
+
+    let mut instructions = Vec::with_capacity(2);
+    let fee_up_to_date = match account {
+        Some(account) => {
+            let oracle =
+                Oracle::try_deserialize(&mut &account.data[..]).context("Deserialize Oracle")?;
+
+            if oracle.vrf_public_key.0[..] != vrf_public_key[..] {
+                tracing::warn!("The registered VRF public key does not match vrf-private-key, proofs will be rejected");
+            }
+            if oracle.status != OracleStatus::Active {
+                tracing::warn!("The oracle is disabled, no new request will be bound to it");
+            }
+
+            (oracle.fee, oracle.fee_mint, oracle.fee_vault)
+                == (config.fee, config.fee_mint, config.fee_vault)
+        }
+        None => {
+            tracing::info!("Registering oracle: {}", &config.oracle);
+
+            let mut data = coordinator::instruction::REGISTER_ORACLE.to_vec();
+            data.extend_from_slice(&vrf_public_key);
+
+            instructions.push(Instruction {
+                program_id: coordinator::ID,
+                accounts: vec![
+                    AccountMeta::new(config.signer.pubkey(), true),
+                    AccountMeta::new(config.oracle, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data,
+            });
+
+            // registered with no fee, paid to the signer
+            (0, Pubkey::default(), config.signer.pubkey())
+                == (config.fee, config.fee_mint, config.fee_vault)
+        }
+    };
+
+    if !fee_up_to_date {
+        tracing::info!(
+            "Setting oracle fee: {} (mint: {}, vault: {})",
+            config.fee,
+            config.fee_mint,
+            config.fee_vault
+        );
+
+        let mut data = coordinator::instruction::SET_ORACLE_FEE.to_vec();
+        (config.fee, config.fee_mint, config.fee_vault).serialize(&mut data)?;
+
+        instructions.push(Instruction {
+            program_id: coordinator::ID,
+            accounts: vec![
+                AccountMeta::new_readonly(config.signer.pubkey(), true),
+                AccountMeta::new(config.oracle, false),
+            ],
+            data,
+        });
+    }
+
+    if instructions.is_empty() {
+        return Ok(());
+    }
+
+    let latest_hash = rpc_client.get_latest_blockhash().await?;
+    let trans = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&config.signer.pubkey()),
+        &[&config.signer],
+        latest_hash,
+    );
+
+    rpc_client
+        .send_and_confirm_transaction(&trans)
+        .await
+        .context("Update oracle registry")?;
+
+    Ok(())
+}
@@ -5,28 +5,46 @@ use anchor_client::{
     solana_client::{
         client_error::ClientErrorKind,
         nonblocking::rpc_client::RpcClient,
+        rpc_config::RpcTransactionConfig,
         rpc_request::{RpcError, RpcResponseErrorData},
         rpc_response::RpcSimulateTransactionResult,
     },
     solana_sdk::{
+        address_lookup_table_account::AddressLookupTableAccount,
+        commitment_config::CommitmentConfig,
+        compute_budget::ComputeBudgetInstruction,
+        hash::Hash,
         instruction::{AccountMeta, Instruction},
-        signature::Signer,
-        transaction::{Transaction, TransactionError},
+        message::{v0, Message, VersionedMessage},
+        signature::{Signature, Signer},
+        transaction::{TransactionError, VersionedTransaction},
     },
 };
 use anyhow::Context;
 use backoff::{backoff::Backoff, ExponentialBackoff};
 use once_cell::unsync::Lazy;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedTransaction, UiMessage, UiTransactionEncoding,
+    UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
 use vrf::{
     openssl::{CipherSuite, ECVRF},
     VRF,
 };
 use vrf_sdk::{
     __private::Pubkey,
-    vrf::{VrfAccountData, VrfRequestRandomness, RESULT_BYTE_LEN},
+    coordinator,
+    vrf::{
+        batch_seeds, lookup_table_addresses, CallbackRef, VrfAccountView, VrfRequestCancelled,
+        VrfRequestRandomness, VrfStatus, PROOF_BYTE_LEN, RESULT_BYTE_LEN, SEEDS_BYTE_LEN,
+    },
 };
 
-use crate::{config::VrfConfig, parse_logs::parse_logs};
+use crate::{
+    config::VrfConfig,
+    ledger::{Ledger, LedgerRequest},
+    parse_logs::parse_logs,
+};
 
 thread_local! {
     static VRF: RefCell<Lazy<ECVRF>> = RefCell::new(Lazy::new(|| {
@@ -34,19 +52,93 @@ thread_local! {
     }));
 }
 
+/// Compute unit limit requested when the callback verify the proof on-chain
+const VERIFY_PROOF_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
 pub struct VrfResponse {
-    pub response_transaction: String,
-    pub seeds: Vec<u8>,
-    pub proof: Vec<u8>,
+    /// One per slot sent of a batch request, a single one otherwise.
+    pub response_transactions: Vec<String>,
+    /// One output per slot of a batch request, a single one otherwise.
+    pub outputs: Vec<VrfOutput>,
+}
+
+/// The transaction whose logs are processed.
+pub struct LogsTransaction<'a> {
+    pub signature: &'a str,
+    pub slot: u64,
+}
+
+/// The randomness of a request, or of one slot of a batch request.
+pub struct VrfOutput {
+    pub seeds: [u8; SEEDS_BYTE_LEN],
+    pub result: [u8; RESULT_BYTE_LEN],
+    pub proof: [u8; PROOF_BYTE_LEN],
+}
+
+/// What [`process`] did with a request.
+pub enum Processed {
+    /// The request is not ours to fulfill, or no longer needs to be.
+    Skipped(SkipReason),
+    Fulfilled(VrfResponse),
+}
+
+/// A request of the processed transaction, in log order.
+pub struct ProcessedRequest {
+    pub vrf: Pubkey,
+    pub round: u64,
+    /// A failure only concerns this request, the others of the transaction are still processed.
+    pub processed: anyhow::Result<Processed>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    Closed,
+    OtherOracle(Pubkey),
+    UnpaidFee {
+        paid: u64,
+        expected: u64,
+    },
+    NotPending(VrfStatus),
+    StaleRound {
+        request: u64,
+        account: u64,
+    },
+    UnknownCallbackProgram(Pubkey),
+    /// Another task is fulfilling the same request.
+    InFlight,
 }
 
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "the vrf account is closed"),
+            Self::OtherOracle(oracle) => write!(f, "bound to another oracle: {oracle}"),
+            Self::UnpaidFee { paid, expected } => {
+                write!(f, "unpaid fee: paid {paid}, expected {expected}")
+            }
+            Self::NotPending(status) => write!(f, "not pending: {status:?}"),
+            Self::StaleRound { request, account } => write!(
+                f,
+                "stale request of round {request}, the account is at round {account}"
+            ),
+            Self::UnknownCallbackProgram(program) => {
+                write!(f, "callback to an unknown program: {program}")
+            }
+            Self::InFlight => write!(f, "already being fulfilled"),
+        }
+    }
+}
+
+/// Process every request of the transaction of `program_id`, and record the cancelled ones.
 pub async fn process<S: AsRef<str>>(
     config: &VrfConfig,
     rpc_client: &RpcClient,
+    ledger: &Ledger,
     program_id: &Pubkey,
+    transaction: &LogsTransaction<'_>,
     span: &tracing::Span,
     logs: &[S],
-) -> anyhow::Result<Option<VrfResponse>> {
+) -> anyhow::Result<Vec<ProcessedRequest>> {
     let (events, errors) = parse_logs(&logs, &config.program_ids);
 
     if !errors.is_empty() {
@@ -58,106 +150,311 @@ pub async fn process<S: AsRef<str>>(
         ));
     }
 
-    let event = {
-        let event = events
-            .into_iter()
-            .filter(|event| VrfRequestRandomness::discriminator() == event.data[0..8])
-            .next();
+    // The coordinator transfers the fee of its requests to our fee vault as well
+    let coordinator_requests = events
+        .iter()
+        .filter(|event| {
+            VrfRequestRandomness::discriminator() == event.data[0..8]
+                && event.program_id == coordinator::ID
+        })
+        .count() as u64;
+    let mut deposit = None;
+
+    let mut processed = vec![];
+    for (index, event) in events.iter().enumerate() {
+        if VrfRequestCancelled::discriminator() == event.data[0..8] {
+            let cancelled = VrfRequestCancelled::deserialize(&mut &event.data[8..])
+                .context("Deserialize VrfRequestCancelled Event")?;
+            span.in_scope(|| tracing::info!("Request cancelled: {}", cancelled.vrf));
+
+            // The account may be re-armed later in the same transaction, that new round is still pending
+            let rearmed = events[index + 1..]
+                .iter()
+                .filter(|event| VrfRequestRandomness::discriminator() == event.data[0..8])
+                .filter_map(|event| deserialize_request_event(&event.data[8..]).ok())
+                .find(|request| request.vrf == cancelled.vrf);
+            ledger
+                .record_cancelled(&cancelled.vrf, rearmed.map(|request| request.round))
+                .context("Record cancelled request")?;
+            continue;
+        }
 
-        match event {
-            Some(event) => event,
-            None => return Ok(None),
+        // The requests of the other programs are processed by their own subscription
+        if VrfRequestRandomness::discriminator() != event.data[0..8]
+            || &event.program_id != program_id
+        {
+            continue;
         }
+
+        let request_vrf = deserialize_request_event(&event.data[8..])?;
+        processed.push(ProcessedRequest {
+            vrf: request_vrf.vrf,
+            round: request_vrf.round,
+            processed: process_request(
+                config,
+                rpc_client,
+                ledger,
+                program_id,
+                transaction,
+                span,
+                &request_vrf,
+                &mut deposit,
+                coordinator_requests,
+            )
+            .await,
+        });
+    }
+
+    Ok(processed)
+}
+
+/// Fulfill the request of `request_vrf`, paid from the `deposit` of the transaction if not
+/// made through the coordinator, fetched by the first request of the transaction needing it.
+#[allow(clippy::too_many_arguments)]
+async fn process_request(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_id: &Pubkey,
+    transaction: &LogsTransaction<'_>,
+    span: &tracing::Span,
+    request_vrf: &VrfRequestRandomness,
+    deposit: &mut Option<FeeDeposit>,
+    coordinator_requests: u64,
+) -> anyhow::Result<Processed> {
+    let vrf_account = match rpc_client
+        .get_account_with_commitment(&request_vrf.vrf, rpc_client.commitment())
+        .await?
+        .value
+    {
+        Some(account) => account,
+        None => return Ok(Processed::Skipped(SkipReason::Closed)),
     };
+    // Both the full and the compact layouts are read in place,
+    // failing for a layout version newer than our sdk
+    let vrf_account_data = VrfAccountView::from_account_data(&vrf_account.data)
+        .map_err(|err| anyhow::anyhow!("Invalid vrf account: {err}"))?;
 
-    if &event.program_id != program_id {
-        return Err(anyhow::anyhow!("program_id not match"));
+    if vrf_account_data.oracle() != config.oracle {
+        return Ok(Processed::Skipped(SkipReason::OtherOracle(
+            vrf_account_data.oracle(),
+        )));
     }
 
-    let request_vrf = VrfRequestRandomness::deserialize(&mut &event.data[8..])
-        .context("Deserialize RequestVrf Event")?;
+    // Only the coordinator is trusted to record the fee it transferred, a direct
+    // or native request is written by the requesting program itself
+    if config.fee > 0 {
+        if vrf_account.owner == coordinator::ID {
+            let paid = vrf_account_data.fee_paid();
+            if paid < config.fee {
+                return Ok(Processed::Skipped(SkipReason::UnpaidFee {
+                    paid,
+                    expected: config.fee,
+                }));
+            }
+        } else {
+            let deposit = match deposit {
+                Some(deposit) => deposit,
+                None => deposit.insert(FeeDeposit::new(
+                    fee_deposit(config, rpc_client, transaction.signature).await?,
+                    config.fee,
+                    coordinator_requests,
+                )),
+            };
+            if let Err(reason) = deposit.spend(config.fee) {
+                return Ok(Processed::Skipped(reason));
+            }
+        }
+    }
 
-    let vrf_account_data = rpc_client.get_account_data(&request_vrf.vrf).await?;
-    if vrf_account_data[0..8] != VrfAccountData::DISCRIMINATOR {
-        return Err(anyhow::anyhow!("invalid discriminator"));
+    if let Some(reason) = settled_request(&vrf_account_data, request_vrf.round)? {
+        return Ok(Processed::Skipped(reason));
     }
 
-    let vrf_account_data: &VrfAccountData =
-        bytemuck::from_bytes(&vrf_account_data[8..std::mem::size_of::<VrfAccountData>() + 8]);
+    // The coordinator invoke the callback on behalf of any program,
+    // a direct callback is sent by ourself so only to the programs we trust
+    let callback_program = vrf_account_data.callback().program_id;
+    if vrf_account.owner != coordinator::ID
+        && !config.is_callback_allowed(program_id, &callback_program)
+    {
+        return Ok(Processed::Skipped(SkipReason::UnknownCallbackProgram(
+            callback_program,
+        )));
+    }
 
-    let (proof, random) = {
-        let (proof, hash) = VRF.with(|vrf| {
-            let mut vrf = vrf.borrow_mut();
-            let proof = vrf
-                .prove(&config.vrf_secret, &vrf_account_data.seeds)
-                .unwrap();
-            let hash = vrf.proof_to_hash(&proof).unwrap();
-            (proof, hash)
-        });
+    // The live logs and the old transactions may deliver the same request,
+    // only the first task fulfill it, until it is done
+    let Some(_in_flight) = ledger.claim(&request_vrf.vrf, request_vrf.round) else {
+        return Ok(Processed::Skipped(SkipReason::InFlight));
+    };
 
-        let mut random = [0u8; RESULT_BYTE_LEN];
-        random.copy_from_slice(&hash[..RESULT_BYTE_LEN]);
-        (proof, random)
+    // A batch request has one output per slot, fulfilled in slot order
+    let seeds = vrf_account_data.seeds();
+    let outputs = match vrf_account_data.batch_len() {
+        0 => vec![prove(config, seeds)?],
+        len => (0..len)
+            .map(|slot| prove(config, batch_seeds(&seeds, slot)))
+            .collect::<anyhow::Result<Vec<_>>>()?,
     };
 
-    span.in_scope(|| tracing::info!("Random value: {:?}", &random));
+    span.in_scope(|| {
+        for output in &outputs {
+            tracing::info!("Random value: {:?}", &output.result);
+        }
+    });
+
+    // Recorded before sending, so a response landing on-chain always has its proof in the ledger
+    ledger
+        .record_request(
+            &LedgerRequest {
+                vrf: request_vrf.vrf,
+                round: request_vrf.round,
+                program_id: *program_id,
+                signature: transaction.signature,
+                slot: transaction.slot,
+            },
+            &outputs,
+        )
+        .context("Record request")?;
 
-    let mut trans = {
-        let cb = vrf_account_data.callback;
+    // Callback accounts stored in an address lookup table are resolved through a v0 transaction
+    let lookup = vrf_account_data.lookup();
+    let (lookup_table, callback_accounts) = if lookup.is_used() {
+        let key = lookup.table;
+        let table = rpc_client
+            .get_account(&key)
+            .await
+            .with_context(|| format!("Fetch lookup table {key}"))?;
+        let invalid_table = |err| anyhow::anyhow!("Invalid lookup table {key}: {err}");
 
-        let mut ix_data = cb.ix_data[0..cb.ix_data_len as usize].to_vec();
-        if let Some((offset, _)) = ix_data
-            .windows(RESULT_BYTE_LEN)
-            .enumerate()
-            .find(|(_, slice)| slice == &vrf_sdk::vrf::VRF_RESULT_DISCRIMINATOR)
+        let callback_accounts = vrf_account_data
+            .callback_account_metas(Some(&table.data))
+            .map_err(invalid_table)?;
+        let addresses = lookup_table_addresses(&table.data).map_err(invalid_table)?;
+        (
+            Some(AddressLookupTableAccount { key, addresses }),
+            callback_accounts,
+        )
+    } else {
+        (None, vrf_account_data.callback_account_metas(None)?)
+    };
+
+    // the coordinator does not open batch requests
+    if vrf_account.owner == coordinator::ID && outputs.len() != 1 {
+        return Err(anyhow::anyhow!("Batch request through the coordinator"));
+    }
+
+    // Verifying a proof on-chain takes most of the compute budget of a transaction,
+    // so each slot of a batch is sent in its own transaction, in slot order,
+    // resuming after the slots already fulfilled
+    let slots = match vrf_account_data.batch_len() {
+        0 => 0..1,
+        len => vrf_account_data.batch_fulfilled()..len,
+    };
+    let mut response_transactions = Vec::with_capacity(slots.len());
+    for slot in slots {
+        let output = &outputs[slot as usize];
+        let instructions = if vrf_account.owner == coordinator::ID {
+            coordinator_instructions(
+                config,
+                &request_vrf.vrf,
+                lookup_table.as_ref(),
+                callback_accounts.clone(),
+                vrf_account_data.callback(),
+                output,
+            )
+        } else {
+            callback_instructions(
+                callback_accounts.clone(),
+                vrf_account_data.callback(),
+                output,
+            )?
+        };
+
+        let signature = match send_transaction(
+            config,
+            rpc_client,
+            &instructions,
+            lookup_table.as_ref(),
+            span,
+        )
+        .await
         {
-            if offset != 8 {
-                span.in_scope(|| {
-                    tracing::warn!(
-                        "VrfResult maybe not the first parameters, offset={}",
-                        offset
-                    )
-                });
+            Ok(signature) => signature,
+            Err(err) => {
+                // Fulfilled or closed since we checked, e.g. by another instance of the server,
+                // the simulation failure is expected then
+                let account = rpc_client
+                    .get_account_with_commitment(&request_vrf.vrf, rpc_client.commitment())
+                    .await
+                    .ok()
+                    .map(|response| response.value);
+                let reason = match account {
+                    Some(None) => Some(SkipReason::Closed),
+                    Some(Some(account)) => VrfAccountView::from_account_data(&account.data)
+                        .ok()
+                        .and_then(|view| settled_request(&view, request_vrf.round).ok().flatten()),
+                    None => None,
+                };
+
+                return match reason {
+                    Some(reason) => Ok(Processed::Skipped(reason)),
+                    None => Err(err),
+                };
             }
+        };
 
-            ix_data[offset..offset + RESULT_BYTE_LEN].copy_from_slice(&random);
-        } else {
-            return Err(anyhow::anyhow!("cannot found VrfResult in ix_data"));
+        // the response is on-chain, a ledger failure must not report it as failed
+        let signature = signature.to_string();
+        if let Err(err) =
+            ledger.record_response(&request_vrf.vrf, request_vrf.round, slot, &signature)
+        {
+            span.in_scope(|| tracing::error!("Error recording response:\n{err:#}"));
         }
+        response_transactions.push(signature);
+    }
 
-        let instruction = Instruction {
-            program_id: *program_id,
-            data: ix_data,
-            accounts: cb.accounts[0..cb.accounts_len as usize]
-                .iter()
-                .map(|acc| AccountMeta {
-                    pubkey: acc.pubkey,
-                    is_signer: acc.is_signer,
-                    is_writable: acc.is_writable,
-                })
-                .collect(),
-        };
+    Ok(Processed::Fulfilled(VrfResponse {
+        response_transactions,
+        outputs,
+    }))
+}
 
-        let latest_hash = rpc_client.get_latest_blockhash().await?;
-        Transaction::new_signed_with_payer(
-            &[instruction],
-            Some(&config.signer.pubkey()),
-            &[&config.signer],
-            latest_hash,
-        )
-    };
+/// Why the request of `round` does not need a response anymore, if so.
+fn settled_request(vrf: &VrfAccountView<'_>, round: u64) -> anyhow::Result<Option<SkipReason>> {
+    match vrf.status() {
+        Ok(VrfStatus::Pending) => {}
+        Ok(status) => return Ok(Some(SkipReason::NotPending(status))),
+        Err(err) => return Err(anyhow::anyhow!("Invalid vrf status: {err}")),
+    }
+
+    // A re-armed account is fulfilled once per round, by the event of that round
+    if round != vrf.round() {
+        return Ok(Some(SkipReason::StaleRound {
+            request: round,
+            account: vrf.round(),
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Send and confirm the response, signed again whenever the blockhash expires.
+async fn send_transaction(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    lookup_table: Option<&AddressLookupTableAccount>,
+    span: &tracing::Span,
+) -> anyhow::Result<Signature> {
+    let latest_hash = rpc_client.get_latest_blockhash().await?;
+    let mut trans = sign_transaction(config, instructions, lookup_table, latest_hash)?;
 
     let mut backoff = ExponentialBackoff::default();
     loop {
         span.in_scope(|| tracing::info!("Sending request..."));
         match rpc_client.send_and_confirm_transaction(&trans).await {
-            Ok(signature) => {
-                return Ok(Some(VrfResponse {
-                    response_transaction: signature.to_string(),
-                    seeds: vrf_account_data.seeds.to_vec(),
-                    proof: proof.clone(),
-                }))
-            }
+            Ok(signature) => return Ok(signature),
             Err(err) => match err.kind() {
                 ClientErrorKind::RpcError(RpcError::RpcResponseError { data, .. }) => {
                     if let RpcResponseErrorData::SendTransactionPreflightFailure(
@@ -181,11 +478,13 @@ pub async fn process<S: AsRef<str>>(
                 ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound)
                 | ClientErrorKind::TransactionError(TransactionError::AlreadyProcessed) => {
                     let new_blockhash = rpc_client
-                        .get_new_latest_blockhash(&trans.message.recent_blockhash)
+                        .get_new_latest_blockhash(trans.message.recent_blockhash())
                         .await;
 
+                    // the transaction must be signed again with the new blockhash
                     if let Ok(new_blockhash) = new_blockhash {
-                        trans.message.recent_blockhash = new_blockhash;
+                        trans =
+                            sign_transaction(config, instructions, lookup_table, new_blockhash)?;
                     }
                 }
                 _ => return Err(err)?,
@@ -198,3 +497,595 @@ pub async fn process<S: AsRef<str>>(
         }
     }
 }
+
+/// Requests emitted before the round counter only carry the vrf account, their round is `0`.
+fn deserialize_request_event(data: &[u8]) -> anyhow::Result<VrfRequestRandomness> {
+    match VrfRequestRandomness::deserialize(&mut &data[..]) {
+        Ok(event) => Ok(event),
+        Err(_) => Ok(VrfRequestRandomness {
+            vrf: Pubkey::deserialize(&mut &data[..]).context("Deserialize RequestVrf Event")?,
+            round: 0,
+        }),
+    }
+}
+
+/// Sign the fulfillment, as a v0 transaction if the callback uses an address lookup table.
+fn sign_transaction(
+    config: &VrfConfig,
+    instructions: &[Instruction],
+    lookup_table: Option<&AddressLookupTableAccount>,
+    blockhash: Hash,
+) -> anyhow::Result<VersionedTransaction> {
+    let payer = config.signer.pubkey();
+    let message = match lookup_table {
+        Some(lookup_table) => VersionedMessage::V0(
+            v0::Message::try_compile(
+                &payer,
+                instructions,
+                std::slice::from_ref(lookup_table),
+                blockhash,
+            )
+            .context("Compile v0 message")?,
+        ),
+        None => VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            Some(&payer),
+            &blockhash,
+        )),
+    };
+
+    Ok(VersionedTransaction::try_new(message, &[&config.signer])?)
+}
+
+/// Prove `seeds` with our VRF secret key.
+fn prove(config: &VrfConfig, seeds: [u8; SEEDS_BYTE_LEN]) -> anyhow::Result<VrfOutput> {
+    let (proof, hash) = VRF.with(|vrf| {
+        let mut vrf = vrf.borrow_mut();
+        let proof = vrf.prove(&config.vrf_secret, &seeds).unwrap();
+        let hash = vrf.proof_to_hash(&proof).unwrap();
+        (proof, hash)
+    });
+
+    let mut result = [0u8; RESULT_BYTE_LEN];
+    result.copy_from_slice(&hash[..RESULT_BYTE_LEN]);
+
+    let proof: [u8; PROOF_BYTE_LEN] = proof
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("unexpected proof length: {}", proof.len()))?;
+
+    Ok(VrfOutput {
+        seeds,
+        result,
+        proof,
+    })
+}
+
+/// Invoke the callback directly with `output`, the callback program has to verify the proof by itself.
+fn callback_instructions(
+    callback_accounts: Vec<AccountMeta>,
+    cb: CallbackRef<'_>,
+    output: &VrfOutput,
+) -> anyhow::Result<Vec<Instruction>> {
+    // VrfProof is optional, only supplied if the callback ask for it
+    let mut instructions = Vec::with_capacity(2);
+    if cb.proof_offset().is_some() {
+        // verifying the proof on-chain is expensive
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            VERIFY_PROOF_COMPUTE_UNIT_LIMIT,
+        ));
+    }
+
+    let ix_data = cb
+        .fill_ix_data(&output.result, &output.proof)
+        .ok_or_else(|| anyhow::anyhow!("cannot found VrfResult in ix_data"))?;
+    instructions.push(Instruction {
+        program_id: cb.program_id,
+        data: ix_data,
+        accounts: callback_accounts,
+    });
+
+    Ok(instructions)
+}
+
+/// Fulfill through the coordinator program, which verify the proof,
+/// store the result and then invoke the callback.
+fn coordinator_instructions(
+    config: &VrfConfig,
+    vrf: &Pubkey,
+    lookup_table: Option<&AddressLookupTableAccount>,
+    callback_accounts: Vec<AccountMeta>,
+    cb: CallbackRef<'_>,
+    output: &VrfOutput,
+) -> Vec<Instruction> {
+    let signer = config.signer.pubkey();
+
+    let mut accounts = vec![
+        AccountMeta::new(signer, true),
+        AccountMeta::new_readonly(config.oracle, false),
+        AccountMeta::new(*vrf, false),
+        AccountMeta::new_readonly(coordinator::authority(), false),
+        AccountMeta::new_readonly(cb.program_id, false),
+    ];
+
+    // The callback accounts are chosen by the requester, so our key never sign them,
+    // the coordinator authority will be signed by the coordinator itself.
+    accounts.extend(callback_accounts.into_iter().map(|acc| AccountMeta {
+        is_signer: false,
+        ..acc
+    }));
+    // The coordinator read the lookup table to check the callback accounts
+    if let Some(lookup_table) = lookup_table {
+        accounts.push(AccountMeta::new_readonly(lookup_table.key, false));
+    }
+
+    let mut data = coordinator::instruction::FULFILL.to_vec();
+    data.extend_from_slice(&output.result);
+    data.extend_from_slice(&output.proof);
+
+    vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(VERIFY_PROOF_COMPUTE_UNIT_LIMIT),
+        Instruction {
+            program_id: coordinator::ID,
+            accounts,
+            data,
+        },
+    ]
+}
+
+/// The fee deposited by a request transaction, spent by its direct requests in log order,
+/// so each fulfilled request is paid for once.
+#[derive(Debug, PartialEq, Eq)]
+struct FeeDeposit {
+    remaining: u64,
+}
+
+impl FeeDeposit {
+    /// The `deposit` to our fee vault, less the `fee` of the `coordinator_requests`.
+    fn new(deposit: u64, fee: u64, coordinator_requests: u64) -> Self {
+        Self {
+            remaining: deposit.saturating_sub(fee.saturating_mul(coordinator_requests)),
+        }
+    }
+
+    /// Spend the `fee` of one request, if what is left of the deposit covers it.
+    fn spend(&mut self, fee: u64) -> Result<(), SkipReason> {
+        if self.remaining < fee {
+            return Err(SkipReason::UnpaidFee {
+                paid: self.remaining,
+                expected: fee,
+            });
+        }
+
+        self.remaining -= fee;
+        Ok(())
+    }
+}
+
+/// What the request transaction `signature` transferred to our fee vault.
+async fn fee_deposit(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    signature: &str,
+) -> anyhow::Result<u64> {
+    // `getTransaction` does not support the processed commitment
+    let commitment = match rpc_client.commitment() {
+        commitment if commitment.is_at_least_confirmed() => commitment,
+        _ => CommitmentConfig::confirmed(),
+    };
+    let transaction = rpc_client
+        .get_transaction_with_config(
+            &signature
+                .parse::<Signature>()
+                .context("Invalid request signature")?,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .with_context(|| format!("Fetch request transaction {signature}"))?
+        .transaction;
+
+    let account_keys = match transaction.transaction {
+        EncodedTransaction::Json(transaction) => match transaction.message {
+            UiMessage::Raw(message) => message.account_keys,
+            UiMessage::Parsed(_) => anyhow::bail!("Unexpected parsed request transaction"),
+        },
+        _ => anyhow::bail!("Unexpected request transaction encoding"),
+    };
+    let meta = transaction
+        .meta
+        .context("Request transaction without status meta")?;
+
+    Ok(vault_deposit(config, &meta, account_keys))
+}
+
+/// The increase of the fee vault balance, in lamports or in `fee_mint`, over a transaction.
+fn vault_deposit(
+    config: &VrfConfig,
+    meta: &UiTransactionStatusMeta,
+    mut account_keys: Vec<String>,
+) -> u64 {
+    // the accounts of a v0 transaction loaded from lookup tables come after its static keys
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        account_keys.extend(loaded.writable.iter().cloned());
+        account_keys.extend(loaded.readonly.iter().cloned());
+    }
+    let fee_vault = config.fee_vault.to_string();
+    let Some(index) = account_keys.iter().position(|key| *key == fee_vault) else {
+        return 0;
+    };
+
+    if config.fee_mint == Pubkey::default() {
+        let pre = meta.pre_balances.get(index).copied().unwrap_or_default();
+        let post = meta.post_balances.get(index).copied().unwrap_or_default();
+        return post.saturating_sub(pre);
+    }
+
+    let fee_mint = config.fee_mint.to_string();
+    let token_balance = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>| match balances
+    {
+        OptionSerializer::Some(balances) => balances
+            .iter()
+            .find(|balance| usize::from(balance.account_index) == index && balance.mint == fee_mint)
+            .and_then(|balance| balance.ui_token_amount.amount.parse::<u64>().ok())
+            .unwrap_or_default(),
+        _ => 0,
+    };
+    token_balance(&meta.post_token_balances).saturating_sub(token_balance(&meta.pre_token_balances))
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::anchor_lang::AnchorSerialize;
+    use anchor_client::{
+        solana_client::rpc_request::RpcRequest,
+        solana_sdk::{packet::PACKET_DATA_SIZE, signature::Keypair},
+        Cluster,
+    };
+    use base64::Engine;
+    use vrf_sdk::{
+        __private::Zeroable,
+        compact::{account_size, VrfCompactAccountData},
+        vrf::VrfAccountData,
+    };
+
+    use super::*;
+
+    fn config(callback_program_ids: Vec<Pubkey>) -> VrfConfig {
+        let signer = Keypair::new();
+        VrfConfig {
+            oracle: vrf_sdk::oracle::address(&signer.pubkey()),
+            fee_vault: signer.pubkey(),
+            signer,
+            vrf_secret: vec![1; 32],
+            cluster: Cluster::Localnet,
+            commitment: CommitmentConfig::confirmed(),
+            program_ids: vec![],
+            callback_program_ids,
+            fee: 0,
+            fee_mint: Pubkey::default(),
+            ledger_path: String::new(),
+            api_address: None,
+            backfill_concurrency: 1,
+        }
+    }
+
+    #[test]
+    fn test_request_event_round() {
+        let vrf = Pubkey::new_unique();
+
+        let mut data = vec![];
+        VrfRequestRandomness { vrf, round: 7 }
+            .serialize(&mut data)
+            .unwrap();
+        let event = deserialize_request_event(&data).unwrap();
+        assert_eq!((event.vrf, event.round), (vrf, 7));
+
+        // emitted before the round counter
+        let event = deserialize_request_event(vrf.as_ref()).unwrap();
+        assert_eq!((event.vrf, event.round), (vrf, 0));
+
+        assert!(deserialize_request_event(&[1, 2, 3]).is_err());
+    }
+
+    fn output(value: u8) -> VrfOutput {
+        VrfOutput {
+            seeds: [value; SEEDS_BYTE_LEN],
+            result: [value; RESULT_BYTE_LEN],
+            proof: [value; PROOF_BYTE_LEN],
+        }
+    }
+
+    #[test]
+    fn test_vault_deposit() {
+        let mut config = self::config(vec![]);
+        let (payer, fee_vault, fee_mint) = (
+            Pubkey::new_unique().to_string(),
+            config.fee_vault.to_string(),
+            Pubkey::new_unique().to_string(),
+        );
+        let token_balance = |amount: &str| {
+            serde_json::json!([{
+                "accountIndex": 2,
+                "mint": fee_mint,
+                "uiTokenAmount": { "uiAmount": null, "decimals": 0, "amount": amount, "uiAmountString": amount },
+            }])
+        };
+        let meta: UiTransactionStatusMeta = serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [10_000, 0, 1_000],
+            "postBalances": [4_000, 0, 2_000],
+            "preTokenBalances": token_balance("10"),
+            "postTokenBalances": token_balance("25"),
+            "loadedAddresses": { "writable": [fee_vault], "readonly": [] },
+        }))
+        .unwrap();
+        // the fee vault is loaded from a lookup table, after the static keys
+        let account_keys = vec![payer, Pubkey::new_unique().to_string()];
+
+        assert_eq!(vault_deposit(&config, &meta, account_keys.clone()), 1_000);
+
+        config.fee_mint = fee_mint.parse().unwrap();
+        assert_eq!(vault_deposit(&config, &meta, account_keys.clone()), 15);
+
+        config.fee_mint = Pubkey::new_unique();
+        assert_eq!(vault_deposit(&config, &meta, account_keys.clone()), 0);
+
+        // a transaction not touching the fee vault
+        config.fee_vault = Pubkey::new_unique();
+        assert_eq!(vault_deposit(&config, &meta, account_keys), 0);
+    }
+
+    #[test]
+    fn test_fee_deposit_per_request() {
+        // paid for two direct requests and one through the coordinator
+        let mut deposit = FeeDeposit::new(300, 100, 1);
+        assert_eq!(deposit.spend(100), Ok(()));
+        assert_eq!(deposit.spend(100), Ok(()));
+        assert_eq!(
+            deposit.spend(100),
+            Err(SkipReason::UnpaidFee {
+                paid: 0,
+                expected: 100
+            })
+        );
+
+        // the coordinator fees are not spent twice
+        let mut deposit = FeeDeposit::new(100, 100, 2);
+        assert!(deposit.spend(100).is_err());
+    }
+
+    #[test]
+    fn test_callback_allowlist() {
+        let (requester, engine) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let config = self::config(vec![]);
+        assert!(config.is_callback_allowed(&requester, &requester));
+        assert!(!config.is_callback_allowed(&requester, &engine));
+
+        let config = self::config(vec![engine]);
+        assert!(config.is_callback_allowed(&requester, &engine));
+        assert!(!config.is_callback_allowed(&requester, &Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_callback_to_other_program() {
+        let engine = Pubkey::new_unique();
+        let accounts = [AccountMeta::new(Pubkey::new_unique(), false)];
+        let mut ix_data = vec![0u8; 8];
+        ix_data.extend_from_slice(&vrf_sdk::vrf::VRF_RESULT_DISCRIMINATOR);
+
+        // a request whose callback is on another program than the requester
+        let mut data = vec![0u8; account_size(accounts.len(), ix_data.len())];
+        data[0..8].copy_from_slice(&VrfAccountData::DISCRIMINATOR);
+        let (vrf, callback_data) = VrfCompactAccountData::split_mut(&mut data[8..]).unwrap();
+        vrf.layout_version = vrf_sdk::vrf::LAYOUT_VERSION;
+        vrf.set_callback(callback_data, engine, &accounts, &ix_data)
+            .unwrap();
+
+        let view = VrfAccountView::from_account_data(&data).unwrap();
+        let instructions = callback_instructions(
+            view.callback_account_metas(None).unwrap(),
+            view.callback(),
+            &output(7),
+        )
+        .unwrap();
+
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, engine);
+        assert_eq!(instructions[0].accounts, accounts);
+        assert_eq!(&instructions[0].data[8..], &[7; RESULT_BYTE_LEN]);
+    }
+
+    #[test]
+    fn test_batch_callbacks() {
+        let program_id = Pubkey::new_unique();
+        let accounts = [AccountMeta::new(Pubkey::new_unique(), false)];
+        let mut ix_data = vec![0u8; 8];
+        ix_data.extend_from_slice(&vrf_sdk::vrf::VRF_RESULT_DISCRIMINATOR);
+        ix_data.extend_from_slice(&vrf_sdk::VrfProof::default().proof);
+
+        let mut vrf = VrfAccountData::zeroed();
+        vrf.seeds = [1; SEEDS_BYTE_LEN];
+        vrf.callback.program_id = program_id;
+        vrf.callback.accounts_len = 1;
+        vrf.callback.accounts[0] = (&accounts[0]).into();
+        vrf.callback.set_ix_data(&ix_data).unwrap();
+        vrf.set_batch(3).unwrap();
+
+        let seeds = vrf.seeds;
+        let slots = (0..vrf.batch_len())
+            .map(|slot| batch_seeds(&seeds, slot))
+            .collect::<Vec<_>>();
+        assert_eq!(slots.len(), 3);
+        assert_ne!(slots[0], slots[1]);
+
+        // one transaction per slot, each verifying a single proof
+        let config = self::config(vec![]);
+        for (slot, output) in (0..vrf.batch_len()).map(|slot| (slot, output(slot + 1))) {
+            let instructions = callback_instructions(
+                vrf.callback_account_metas(None).unwrap(),
+                vrf.callback.view(),
+                &output,
+            )
+            .unwrap();
+            assert_eq!(instructions.len(), 2);
+            assert_eq!(instructions[1].program_id, program_id);
+            assert_eq!(&instructions[1].data[8..40], &[slot + 1; RESULT_BYTE_LEN]);
+            assert_eq!(&instructions[1].data[40..], &[slot + 1; PROOF_BYTE_LEN]);
+
+            let transaction =
+                sign_transaction(&config, &instructions, None, Hash::default()).unwrap();
+            assert!(bincode::serialize(&transaction).unwrap().len() <= PACKET_DATA_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_settled_request() {
+        let mut vrf = VrfAccountData::zeroed();
+        vrf.header.version = vrf_sdk::vrf::STATUS_HEADER_VERSION;
+        vrf.header.status = VrfStatus::Pending as u8;
+        vrf.round = 2;
+
+        let settled = |vrf: &VrfAccountData, round| {
+            let mut data = VrfAccountData::DISCRIMINATOR.to_vec();
+            data.extend_from_slice(vrf_sdk::__private::bytes_of(vrf));
+            settled_request(&VrfAccountView::from_account_data(&data).unwrap(), round).unwrap()
+        };
+
+        assert_eq!(settled(&vrf, 2), None);
+        assert_eq!(
+            settled(&vrf, 1),
+            Some(SkipReason::StaleRound {
+                request: 1,
+                account: 2
+            })
+        );
+
+        vrf.header.status = VrfStatus::Fulfilled as u8;
+        assert_eq!(
+            settled(&vrf, 2),
+            Some(SkipReason::NotPending(VrfStatus::Fulfilled))
+        );
+    }
+
+    /// The logs of a transaction of `program_id` emitting `events`.
+    fn event_logs(program_id: &Pubkey, events: &[Vec<u8>]) -> Vec<String> {
+        let mut logs = vec![format!("Program {program_id} invoke [1]")];
+        for event in events {
+            logs.push(format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(event)
+            ));
+        }
+        logs.push(format!("Program {program_id} success"));
+        logs
+    }
+
+    fn event<T: Discriminator + AnchorSerialize>(event: T) -> Vec<u8> {
+        let mut data = T::discriminator().to_vec();
+        event.serialize(&mut data).unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn test_two_requests_in_one_transaction() {
+        let program_id = Pubkey::new_unique();
+        let mut config = self::config(vec![]);
+        config.program_ids = vec![program_id];
+        let ledger = Ledger::open_in_memory().unwrap();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        // the first request is bound to another oracle, the second account is closed since
+        let mut vrf = VrfAccountData::zeroed();
+        vrf.header.version = vrf_sdk::vrf::STATUS_HEADER_VERSION;
+        vrf.header.status = VrfStatus::Pending as u8;
+        vrf.oracle = Pubkey::new_unique();
+        let mut data = VrfAccountData::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(vrf_sdk::__private::bytes_of(&vrf));
+        let account = serde_json::json!({
+            "context": { "slot": 1 },
+            "value": {
+                "lamports": 1,
+                "data": [base64::engine::general_purpose::STANDARD.encode(&data), "base64"],
+                "owner": program_id.to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+            },
+        });
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            [(RpcRequest::GetAccountInfo, account)]
+                .into_iter()
+                .collect(),
+        );
+
+        // the second account is cancelled then re-armed in the same transaction
+        ledger
+            .record_request(
+                &LedgerRequest {
+                    vrf: second,
+                    round: 1,
+                    program_id,
+                    signature: "request",
+                    slot: 1,
+                },
+                &[output(1)],
+            )
+            .unwrap();
+        let logs = event_logs(
+            &program_id,
+            &[
+                event(VrfRequestRandomness {
+                    vrf: first,
+                    round: 0,
+                }),
+                event(VrfRequestCancelled { vrf: second }),
+                event(VrfRequestRandomness {
+                    vrf: second,
+                    round: 2,
+                }),
+            ],
+        );
+
+        let transaction = LogsTransaction {
+            signature: "transaction",
+            slot: 2,
+        };
+        let processed = process(
+            &config,
+            &rpc_client,
+            &ledger,
+            &program_id,
+            &transaction,
+            &tracing::Span::none(),
+            &logs,
+        )
+        .await
+        .unwrap();
+
+        let reasons = processed
+            .into_iter()
+            .map(|request| match request.processed {
+                Ok(Processed::Skipped(reason)) => (request.vrf, request.round, reason),
+                _ => panic!("unexpected processing of {}", request.vrf),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            [
+                (first, 0, SkipReason::OtherOracle(vrf.oracle)),
+                (second, 2, SkipReason::Closed),
+            ]
+        );
+        assert_eq!(
+            ledger.entry(&second, 1, 0).unwrap().unwrap().status,
+            crate::ledger::LedgerStatus::Cancelled
+        );
+    }
+}
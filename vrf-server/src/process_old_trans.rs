@@ -1,89 +1,348 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anchor_client::{
     solana_client::{
         nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
-        rpc_response::RpcConfirmedTransactionStatusWithSignature,
+        rpc_config::RpcTransactionConfig, rpc_response::RpcConfirmedTransactionStatusWithSignature,
     },
     solana_sdk::{commitment_config::CommitmentConfig, signature::Signature},
 };
+use anyhow::Context;
+use futures_util::StreamExt;
 use solana_transaction_status::{
     option_serializer::OptionSerializer, UiTransactionEncoding, UiTransactionStatusMeta,
 };
+use vrf_sdk::__private::Pubkey;
+
+use crate::{
+    config::VrfConfig,
+    ledger::{Ledger, LedgerCursor, PendingCursors},
+    process::{LogsTransaction, Processed, ProcessedRequest},
+};
+
+/// The most signatures `getSignaturesForAddress` returns at once.
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+/// The most signatures `getSignatureStatuses` accepts at once.
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+/// How often the cursors are persisted.
+const CURSOR_PERSIST_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Process the transactions of `program_id` newer than `since`, oldest first, and return the newest one:
+/// those sent while the VRF-server was not running, or while the logs subscription was down.
+/// Without `since`, every transaction of the program is processed.
+///
+/// The transactions are registered in `pending` before processing, so the persisted cursor
+/// never passes one of them not done yet, see [`persist_cursor`].
+/// After a transaction failed, the backfill resumes from the persisted cursor instead, which stayed
+/// before the failed transaction, so it is retried.
+pub async fn backfill(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_id: &Pubkey,
+    since: Option<&LedgerCursor>,
+    pending: &Mutex<PendingCursors>,
+) -> anyhow::Result<Option<LedgerCursor>> {
+    let persisted;
+    let since = if pending.lock().unwrap().has_failed() {
+        persisted = ledger.cursor(program_id).context("Read the cursor")?;
+        persisted.as_ref()
+    } else {
+        since
+    };
+    let until = since
+        .map(|since| Signature::from_str(&since.signature))
+        .transpose()
+        .context("Invalid cursor signature")?;
+    let mut signatures = fetch_signatures(rpc_client, program_id, until, config.commitment).await?;
+    if signatures.is_empty() {
+        return Ok(None);
+    }
+    signatures.reverse();
 
-use crate::config::VrfConfig;
+    tracing::info!(
+        "Process old transaction: processing {} transactions of {program_id} since slot {}",
+        signatures.len(),
+        since.map_or(0, |since| since.slot)
+    );
 
-pub async fn process_old_transaction(config: Arc<VrfConfig>, rpc_client: Arc<RpcClient>) {
-    let programs = config
-        .program_ids
+    let cursors = signatures
         .iter()
-        .map(|program_id| (program_id, program_id.to_string()))
+        .map(|trans_sig| LedgerCursor {
+            signature: trans_sig.signature.clone(),
+            slot: trans_sig.slot,
+        })
         .collect::<Vec<_>>();
+    {
+        let mut pending = pending.lock().unwrap();
+        for cursor in &cursors {
+            pending.start(cursor);
+        }
+    }
+
+    let mut finished = 0;
+    let processed = process_signatures(
+        config,
+        rpc_client,
+        ledger,
+        program_id,
+        signatures,
+        |cursor| {
+            pending.lock().unwrap().finish(&cursor);
+            finished += 1;
+            Ok(())
+        },
+    )
+    .await;
+    // the transactions complete in order, the first one not finished failed
+    if processed.is_err() {
+        if let Some(failed) = cursors.get(finished) {
+            pending.lock().unwrap().fail(failed);
+        }
+    }
+
+    processed.map(|_| cursors.last().cloned())
+}
 
-    for (program_pubkey, program_id) in programs.iter() {
-        if let Ok(signatures) = rpc_client
+/// Every `CURSOR_PERSIST_INTERVAL`, move the persisted cursor of `program_id` to the newest
+/// transaction done in `pending` that is finalized, so a restart resumes there.
+///
+/// A transaction not finalized yet could still be dropped, and the backfill would not find it.
+pub async fn persist_cursor(
+    rpc_client: Arc<RpcClient>,
+    ledger: Arc<Ledger>,
+    program_id: Pubkey,
+    pending: Arc<Mutex<PendingCursors>>,
+) -> ! {
+    let mut interval = tokio::time::interval(CURSOR_PERSIST_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let mut done = pending.lock().unwrap().done();
+        done.drain(..done.len().saturating_sub(MAX_SIGNATURE_STATUSES));
+        if done.is_empty() {
+            continue;
+        }
+
+        if let Err(err) = persist_finalized(&rpc_client, &ledger, &program_id, &pending, done).await
+        {
+            tracing::warn!("Persist the cursor of {program_id} failed:\n{err:#}");
+        }
+    }
+}
+
+/// Persist the newest of `done` that is finalized, and forget the transactions up to it.
+async fn persist_finalized(
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_id: &Pubkey,
+    pending: &Mutex<PendingCursors>,
+    done: Vec<LedgerCursor>,
+) -> anyhow::Result<()> {
+    let signatures = done
+        .iter()
+        .map(|cursor| Signature::from_str(&cursor.signature))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid cursor signature")?;
+    let statuses = rpc_client
+        .get_signature_statuses_with_history(&signatures)
+        .await
+        .context("Fetch signature statuses")?
+        .value;
+
+    let finalized = done
+        .iter()
+        .zip(statuses)
+        .rev()
+        .find_map(|(cursor, status)| {
+            status
+                .filter(|status| status.satisfies_commitment(CommitmentConfig::finalized()))
+                .map(|_| cursor)
+        });
+    if let Some(cursor) = finalized {
+        ledger.set_cursor(program_id, cursor)?;
+        pending.lock().unwrap().persisted(cursor);
+    }
+
+    Ok(())
+}
+
+/// Process `signatures` concurrently but complete them in order, calling `done` for each one,
+/// so a cursor never passes a transaction not done yet.
+///
+/// After a transaction failed, the others are still processed but `done` is not called anymore,
+/// so the cursor stays before the failed one and the next backfill retries it.
+async fn process_signatures(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_id: &Pubkey,
+    signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    mut done: impl FnMut(LedgerCursor) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut processed = futures_util::stream::iter(signatures)
+        .map(|trans_sig| async move {
+            let processed = match trans_sig.err {
+                None => process_signature(config, rpc_client, ledger, program_id, &trans_sig).await,
+                Some(_) => Ok(()),
+            };
+            (trans_sig, processed)
+        })
+        .buffered(config.backfill_concurrency);
+
+    let mut failed = None;
+    while let Some((trans_sig, processed)) = processed.next().await {
+        if let Err(err) = processed {
+            tracing::error!(
+                "Error processing old transaction {}:\n{err:#}",
+                trans_sig.signature
+            );
+            failed.get_or_insert(trans_sig.signature);
+            continue;
+        }
+
+        if failed.is_none() {
+            done(LedgerCursor {
+                signature: trans_sig.signature,
+                slot: trans_sig.slot,
+            })?;
+        }
+    }
+
+    match failed {
+        Some(signature) => Err(anyhow::anyhow!(
+            "Stopped before the failed transaction {signature}"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Every signature of `address` newer than `until`, newest first, page by page.
+async fn fetch_signatures(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+    until: Option<Signature>,
+    commitment: CommitmentConfig,
+) -> anyhow::Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+
+    loop {
+        let page = rpc_client
             .get_signatures_for_address_with_config(
-                program_pubkey,
+                address,
                 GetConfirmedSignaturesForAddress2Config {
-                    before: None,
-                    until: None,
-                    limit: None,
-                    commitment: Some(CommitmentConfig::finalized()),
+                    before,
+                    until,
+                    limit: Some(SIGNATURES_PAGE_LIMIT),
+                    commitment: Some(commitment),
                 },
             )
             .await
-        {
-            let fetched_len = signatures.len();
-            let signatures = signatures
-                .into_iter()
-                .filter(|sig| sig.err.is_none())
-                .collect::<Vec<_>>();
-
-            if signatures.is_empty() {
-                continue;
-            }
+            .context("Fetch signatures")?;
 
-            tracing::info!(
-                "Process old transaction: processing {} in {} fetched transactions",
-                signatures.len(),
-                fetched_len
-            );
+        let page_len = page.len();
+        let oldest = page
+            .last()
+            .map(|sig| Signature::from_str(&sig.signature))
+            .transpose()
+            .context("invalid signature return from get_signatures")?;
+        signatures.extend(page);
+
+        match oldest {
+            Some(oldest) if page_len == SIGNATURES_PAGE_LIMIT => before = Some(oldest),
+            _ => return Ok(signatures),
+        }
+    }
+}
+
+/// Fetch and process one transaction, failing if it could not be fetched or processed.
+async fn process_signature(
+    config: &VrfConfig,
+    rpc_client: &RpcClient,
+    ledger: &Ledger,
+    program_pubkey: &Pubkey,
+    trans_sig: &RpcConfirmedTransactionStatusWithSignature,
+) -> anyhow::Result<()> {
+    let RpcConfirmedTransactionStatusWithSignature {
+        signature, slot, ..
+    } = trans_sig;
+
+    let encoded_transaction = rpc_client
+        .get_transaction_with_config(
+            &Signature::from_str(signature)
+                .context("invalid signature return from get_signatures")?,
+            // our own fulfillments are v0 transactions when the callback uses a lookup table
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: None,
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await
+        .with_context(|| format!("Fetch transaction {signature}"))?;
 
-            for trans_sig in signatures {
-                let RpcConfirmedTransactionStatusWithSignature { signature, .. } = trans_sig;
-
-                if let Ok(encoded_transaction) = rpc_client
-                    .get_transaction(
-                        &Signature::from_str(&signature)
-                            .expect("invalid signature return from get_signatures"),
-                        UiTransactionEncoding::Json,
-                    )
-                    .await
-                {
-                    if let Some(UiTransactionStatusMeta {
-                        err: None,
-                        log_messages: OptionSerializer::Some(logs),
-                        ..
-                    }) = encoded_transaction.transaction.meta
-                    {
-                        let span = tracing::info_span!(
-                            "Process old transaction",
-                            program_id,
-                            transaction = signature
-                        );
-
-                        if let Err(err) =
-                            crate::process(&config, &rpc_client, program_pubkey, &span, &logs).await
-                        {
-                            span.in_scope(|| {
-                                tracing::error!("Error processing old transaction:\n{err:#}")
-                            });
-                        }
-
-                        span.in_scope(|| tracing::info!("Finished!"));
-                    }
+    if let Some(UiTransactionStatusMeta {
+        err: None,
+        log_messages: OptionSerializer::Some(logs),
+        ..
+    }) = encoded_transaction.transaction.meta
+    {
+        let program_id = program_pubkey.to_string();
+        let span = tracing::info_span!(
+            "Process old transaction",
+            program_id,
+            transaction = signature
+        );
+
+        let transaction = LogsTransaction {
+            signature,
+            slot: *slot,
+        };
+        let processed = crate::process(
+            config,
+            rpc_client,
+            ledger,
+            program_pubkey,
+            &transaction,
+            &span,
+            &logs,
+        )
+        .await
+        .with_context(|| format!("Process transaction {signature}"))?;
+
+        let mut failed = None;
+        for ProcessedRequest {
+            vrf,
+            round,
+            processed,
+        } in processed
+        {
+            match processed {
+                Ok(Processed::Skipped(reason)) => {
+                    span.in_scope(|| tracing::info!("Skipping request {vrf} ({round}): {reason}"));
+                }
+                Ok(Processed::Fulfilled(_)) => {}
+                // the other requests of the transaction are processed all the same
+                Err(err) => {
+                    failed.get_or_insert(err.context(format!(
+                        "Process request {vrf} ({round}) of transaction {signature}"
+                    )));
                 }
             }
         }
+        if let Some(err) = failed {
+            return Err(err);
+        }
+
+        span.in_scope(|| tracing::info!("Finished!"));
     }
+
+    Ok(())
 }